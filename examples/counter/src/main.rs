@@ -1,9 +1,16 @@
-use fiber::{App, StateCtx};
+use fiber::{App, NamedHandler, StateCtx};
+use floem::reactive::use_context;
 
 fn main() {
+    let step = 5;
+
     App::from_path("./examples/counter")
         .enable_logging()
-        .handlers(vec![increase_counter(), decrease_counter()])
+        .handlers(vec![increase_counter(), decrease_counter(), reset_counter()])
+        .handlers([NamedHandler::new("increase_step", move |_| {
+            let state = use_context::<StateCtx>().unwrap();
+            state.update::<i64>("counter", |val| *val += step);
+        })])
         .run();
 }
 
@@ -16,3 +23,8 @@ fn increase_counter(state: StateCtx) {
 fn decrease_counter(state: StateCtx) {
     state.update::<i64>("counter", |val| *val -= 1);
 }
+
+#[fiber::task]
+fn reset_counter(state: StateCtx) {
+    state.set::<i64>("counter", 0);
+}
@@ -0,0 +1,47 @@
+use fiber::{App, EventPayload, StateCtx, Value};
+
+fn main() {
+    App::from_path("./examples/todo")
+        .enable_logging()
+        .handlers(vec![add_todo(), toggle_todo(), remove_todo()])
+        .state(|state| {
+            let todos = vec![todo("Buy milk", true), todo("Write docs", false)];
+            state.insert("todos", todos);
+        })
+        .run();
+}
+
+fn todo(title: &str, done: bool) -> Value {
+    Value::map([("title", Value::from(title)), ("done", Value::from(done))])
+}
+
+#[fiber::task]
+fn add_todo(state: StateCtx) {
+    state.update::<Vec<Value>>("todos", |todos| {
+        let title = format!("Todo #{}", todos.len() + 1);
+        todos.push(todo(&title, false));
+    });
+}
+
+#[fiber::task]
+fn toggle_todo(state: StateCtx, #[arg] index: i64) {
+    state.update::<Vec<Value>>("todos", |todos| {
+        if let Some(Value::Map(todo)) = usize::try_from(index).ok().and_then(|i| todos.get_mut(i)) {
+            let done = todo.get("done").is_some_and(Value::is_truthy);
+            todo.insert("done".to_string(), Value::from(!done));
+        }
+    });
+}
+
+#[fiber::task]
+fn remove_todo(state: StateCtx, #[arg] index: i64, event: EventPayload) {
+    log::info!("Remove todo {index} on {event:?}");
+
+    state.update::<Vec<Value>>("todos", |todos| {
+        if let Ok(i) = usize::try_from(index) {
+            if i < todos.len() {
+                todos.remove(i);
+            }
+        }
+    });
+}
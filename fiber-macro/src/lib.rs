@@ -70,18 +70,18 @@ pub fn task(attr: TokenStream, item: TokenStream) -> TokenStream {
         .map(|ts| ts.to_string())
         .collect::<Vec<_>>();
 
-    let fn_pointer_path = if attrs.contains(&"debug".to_string()) {
-        quote! { crate::state::FnPointer }
+    let root = if attrs.contains(&"debug".to_string()) {
+        quote! { crate }
     } else {
-        quote! { fiber::state::FnPointer }
+        quote! { fiber }
     };
 
     attrs.retain(|v| v != "debug");
 
     if input.sig.asyncness.is_some() {
-        task::build_async_task(&input, &attrs, &fn_pointer_path)
+        task::build_async_task(&input, &attrs, &root)
     } else {
-        task::build_sync_task(&input, &fn_pointer_path)
+        task::build_sync_task(&input, &root)
     }
 }
 
@@ -2,12 +2,12 @@ use proc_macro2::Span;
 use quote::quote;
 use syn::punctuated::Punctuated;
 use syn::token::Comma;
-use syn::{FnArg, Ident, ItemFn, PatType, ReturnType, Type};
+use syn::{FnArg, Ident, ItemFn, Pat, PatType, ReturnType, Type};
 
 pub(crate) fn build_async_task(
     input: &ItemFn,
     attrs: &[String],
-    fn_pointer_path: &proc_macro2::TokenStream,
+    root: &proc_macro2::TokenStream,
 ) -> proc_macro::TokenStream {
     let callback_name = attrs
         .first()
@@ -25,6 +25,15 @@ pub(crate) fn build_async_task(
         panic!("fiber::async_func must have a return type!")
     };
 
+    let inputs = Inputs::parse(&input.sig.inputs);
+
+    assert!(
+        inputs.context_names.is_empty(),
+        "Async task can only take #[arg] and EventPayload parameters"
+    );
+
+    let handler_args = inputs.handler_args();
+
     let fn_name_string = fn_name.to_string();
     let fn_name_wrapper_string = format!("_fibr_{fn_name_string}");
     let fn_name_wrapper = Ident::new(&fn_name_wrapper_string, Span::call_site());
@@ -32,8 +41,10 @@ pub(crate) fn build_async_task(
     let block = &input.block;
 
     quote! {
-        fn #fn_name_wrapper() {
-            let task = async {
+        fn #fn_name_wrapper(_fibr_args: &#root::handler::HandlerArgs) {
+            #handler_args
+
+            let task = async move {
                 #block
             };
 
@@ -45,7 +56,7 @@ pub(crate) fn build_async_task(
             fiber::task::spawn(task);
         }
 
-        fn #fn_name() -> (String, #fn_pointer_path) {
+        fn #fn_name() -> (String, #root::state::FnPointer) {
             (#fn_name_wrapper_string.to_string(), #fn_name_wrapper)
         }
     }
@@ -54,16 +65,19 @@ pub(crate) fn build_async_task(
 
 pub(crate) fn build_sync_task(
     input: &ItemFn,
-    fn_pointer_path: &proc_macro2::TokenStream,
+    root: &proc_macro2::TokenStream,
 ) -> proc_macro::TokenStream {
     let fn_name = &input.sig.ident;
 
-    let (names, types) = parse_inputs(&input.sig.inputs);
+    let inputs = Inputs::parse(&input.sig.inputs);
+    let (names, types) = (&inputs.context_names, &inputs.context_types);
 
     let injects = quote! {
         #(let #names = floem::reactive::use_context::<#types>().expect(&format!("Context item {} not configured", stringify!(#names)));)*
     };
 
+    let handler_args = inputs.handler_args();
+
     assert!(
         matches!(&input.sig.output, ReturnType::Default),
         "This function cannot return a value. Use use_context to access state."
@@ -76,36 +90,85 @@ pub(crate) fn build_sync_task(
     let fn_name_wrapper = Ident::new(&fn_name_wrapper_string, Span::call_site());
 
     quote! {
-        fn #fn_name_wrapper() {
+        fn #fn_name_wrapper(_fibr_args: &#root::handler::HandlerArgs) {
             #injects
 
+            #handler_args
+
             #block
         }
 
-        fn #fn_name() -> (String, #fn_pointer_path) {
+        fn #fn_name() -> (String, #root::state::FnPointer) {
             (#fn_name_wrapper_string.to_string(), #fn_name_wrapper)
         }
     }
     .into()
 }
 
-fn parse_inputs(inputs: &Punctuated<FnArg, Comma>) -> (Vec<&Ident>, Vec<&Type>) {
-    let mut names = Vec::with_capacity(inputs.len());
-    let mut types = Vec::with_capacity(inputs.len());
-
-    for input in inputs {
-        match input {
-            FnArg::Typed(PatType { pat, ty, .. }) => {
-                if let syn::Pat::Ident(ident) = &**pat {
-                    names.push(&ident.ident);
-                    types.push(&**ty);
-                } else {
-                    panic!("Only named arguments are allowed in fiber::func");
-                }
+/// Task parameters by where their value comes from. Parameters marked with `#[arg]` take the
+/// FML arguments in order, an `EventPayload` parameter takes the event and the rest are
+/// context items.
+#[derive(Default)]
+struct Inputs<'a> {
+    context_names: Vec<&'a Ident>,
+    context_types: Vec<&'a Type>,
+    arg_pats: Vec<&'a Pat>,
+    arg_names: Vec<&'a Ident>,
+    arg_types: Vec<&'a Type>,
+    payload_pats: Vec<&'a Pat>,
+    payload_types: Vec<&'a Type>,
+}
+
+impl<'a> Inputs<'a> {
+    fn parse(inputs: &'a Punctuated<FnArg, Comma>) -> Self {
+        let mut parsed = Inputs::default();
+
+        for input in inputs {
+            let FnArg::Typed(PatType { attrs, pat, ty, .. }) = input else {
+                panic!("Only named arguments are allowed in fiber::func");
+            };
+
+            let Pat::Ident(ident) = &**pat else {
+                panic!("Only named arguments are allowed in fiber::func");
+            };
+
+            if attrs.iter().any(|a| a.path().is_ident("arg")) {
+                parsed.arg_pats.push(pat);
+                parsed.arg_names.push(&ident.ident);
+                parsed.arg_types.push(ty);
+            } else if is_payload(ty) {
+                parsed.payload_pats.push(pat);
+                parsed.payload_types.push(ty);
+            } else {
+                parsed.context_names.push(&ident.ident);
+                parsed.context_types.push(ty);
             }
-            FnArg::Receiver(_) => panic!("Only named arguments are allowed in fiber::func"),
         }
+
+        parsed
     }
 
-    (names, types)
+    /// Bindings for `#[arg]` and `EventPayload` parameters. The task returns early if an
+    /// argument is missing or has wrong type.
+    fn handler_args(&self) -> proc_macro2::TokenStream {
+        let (arg_pats, arg_names, arg_types) = (&self.arg_pats, &self.arg_names, &self.arg_types);
+        let (payload_pats, payload_types) = (&self.payload_pats, &self.payload_types);
+        let indices = 0..arg_pats.len();
+
+        quote! {
+            #(let Some(#arg_pats) = _fibr_args.arg::<#arg_types>(#indices, stringify!(#arg_names)) else { return; };)*
+            #(let #payload_pats: #payload_types = _fibr_args.payload.clone();)*
+        }
+    }
+}
+
+fn is_payload(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+
+    path.path
+        .segments
+        .last()
+        .is_some_and(|s| s.ident == "EventPayload")
 }
@@ -2,12 +2,14 @@ use std::path::{Path, PathBuf};
 
 use log::LevelFilter;
 
-use crate::state::{FnPointer, State};
+use crate::handler::NamedHandler;
+use crate::state::State;
 
 pub struct App {
     path: PathBuf,
     state: State,
-    handlers: Option<Vec<(String, FnPointer)>>,
+    handlers: Vec<NamedHandler>,
+    editor: Option<String>,
 }
 
 impl Default for App {
@@ -23,7 +25,8 @@ impl App {
         App {
             state: State::default(),
             path,
-            handlers: None,
+            handlers: Vec::new(),
+            editor: None,
         }
     }
 
@@ -55,13 +58,28 @@ impl App {
         App {
             state: State::default(),
             path,
-            handlers: None,
+            handlers: Vec::new(),
+            editor: None,
         }
     }
 
+    /// Adds handlers. Takes `#[fiber::task]` functions, `(name, fn)` pairs or closures
+    /// wrapped in `NamedHandler`. Can be called more than once.
     #[must_use]
-    pub fn handlers(mut self, handlers: Vec<(String, FnPointer)>) -> Self {
-        self.handlers = Some(handlers);
+    pub fn handlers<H: Into<NamedHandler>>(
+        mut self,
+        handlers: impl IntoIterator<Item = H>,
+    ) -> Self {
+        self.handlers.extend(handlers.into_iter().map(Into::into));
+        self
+    }
+
+    /// Command that opens a file in an editor, like `code --goto {file}:{line}:{col}`. Alt-click
+    /// on a view in debug builds runs it with the location of the element. Defaults to the
+    /// `FIBER_EDITOR` environment variable.
+    #[must_use]
+    pub fn editor(mut self, command: impl Into<String>) -> Self {
+        self.editor = Some(command.into());
         self
     }
 
@@ -76,20 +94,21 @@ impl App {
     #[cfg(debug_assertions)]
     pub fn run(mut self) {
         use floem::ext_event::create_signal_from_channel;
-        use floem::reactive::{create_effect, provide_context, RwSignal};
-        use floem::views::{dyn_view, Decorators};
+        use floem::reactive::{create_effect, create_trigger, provide_context, RwSignal};
+        use floem::style::Style;
+        use floem::views::{dyn_view, stack, Decorators};
         use floem::IntoView;
 
+        use crate::assets::Assets;
         use crate::observer::SourceObserver;
+        use crate::overlay::error_overlay;
         use crate::theme::{theme_provider, StyleCss, Theme, ThemeOptions};
         use crate::{builders, StateCtx};
 
         self.state.read_vars(&self.path.join("main.vars"));
 
-        if let Some(handlers) = self.handlers.take() {
-            for h in handlers {
-                self.state.add_handler(h);
-            }
+        for h in std::mem::take(&mut self.handlers) {
+            self.state.add_handler(h);
         }
 
         let (sender, receiver) = crossbeam_channel::unbounded();
@@ -103,14 +122,66 @@ impl App {
         let observer_event = create_signal_from_channel(receiver.clone());
         let theme_event = create_signal_from_channel(theme.get_untracked().channel.1);
 
+        let errors = RwSignal::new(observer.with_untracked(|o| o.errors().to_vec()));
+        log_errors(&errors.get_untracked());
+
+        let live = builders::LiveTree::default();
+        let rebuild = create_trigger();
+        let assets = Assets::new(&self.path);
+
         provide_context(observer);
-        provide_context(state);
+        provide_context(state.clone());
         provide_context(theme);
+        provide_context(live.clone());
+        provide_context(assets.clone());
+
+        if let Some(command) = self
+            .editor
+            .take()
+            .or_else(|| std::env::var("FIBER_EDITOR").ok())
+        {
+            provide_context(builders::EditorCommand(command));
+        }
+
+        let validate = move || {
+            let diagnostics = observer
+                .with_untracked(|o| theme.with_untracked(|theme| o.validate(&state, theme)));
+            log_diagnostics(&diagnostics);
+        };
+
+        validate();
 
         create_effect(move |_| {
             if observer_event.get().is_some() {
-                observer.update(SourceObserver::update);
-                log::info!("Sources reloaded");
+                // Any file can be an asset
+                assets.reload();
+
+                // Views are rebuilt only from sources that parse
+                let mut result = Ok(());
+                observer.update(|o| result = o.update());
+
+                match result {
+                    Ok(()) => {
+                        // Changes in the main file and components are applied in place
+                        let main = observer.with_untracked(|o| o.main().to_string());
+                        let updated = live.update(&main);
+
+                        errors.set(Vec::new());
+
+                        if updated {
+                            log::info!("Sources reloaded");
+                        } else {
+                            rebuild.notify();
+                            log::info!("Sources reloaded, view rebuilt");
+                        }
+
+                        validate();
+                    }
+                    Err(e) => {
+                        log_errors(&e);
+                        errors.set(e);
+                    }
+                }
             }
         });
 
@@ -123,9 +194,16 @@ impl App {
 
         let theme_provider = theme_provider(
             move || {
-                dyn_view(move || observer.with(|rt| builders::source(rt.main()).into_any()))
-                    .css("body")
-                    .debug_name("Body")
+                let body = dyn_view(move || {
+                    rebuild.track();
+                    observer.with_untracked(|rt| {
+                        builders::source(rt.main(), &rt.main_path()).into_any()
+                    })
+                })
+                .css("body")
+                .debug_name("Body");
+
+                stack((body, error_overlay(errors))).style(Style::size_full)
             },
             ThemeOptions::with_path(self.path.join("styles")),
         );
@@ -138,10 +216,8 @@ impl App {
     pub fn run(mut self) {
         self.state.read_vars(&self.path.join("main.vars"));
 
-        if let Some(handlers) = self.handlers.take() {
-            for h in handlers {
-                self.state.add_handler(h);
-            }
+        for h in std::mem::take(&mut self.handlers) {
+            self.state.add_handler(h);
         }
 
         let state = StateCtx::new(self.state);
@@ -149,13 +225,17 @@ impl App {
 
         provide_context(state);
         provide_context(theme);
+        provide_context(crate::assets::Assets::new(&self.path));
 
         let theme_provider = theme_provider(
             move || {
                 // TODO This probably don't need to be dyn_view on release build and could be
                 // TODO scoped down to specific views/nodes
                 dyn_view(move || {
-                    builders::source(&include_str!("../../examples/stateful/fiber/main.fml"))
+                    builders::source(
+                        include_str!("../../examples/stateful/fiber/main.fml"),
+                        Path::new("main.fml"),
+                    )
                 })
                 .css(&["body"])
                 .debug_name("Body")
@@ -166,3 +246,20 @@ impl App {
         floem::launch(|| theme_provider);
     }
 }
+
+#[cfg(debug_assertions)]
+fn log_errors(errors: &[crate::observer::FileError]) {
+    for e in errors {
+        log::error!("{e}");
+    }
+}
+
+#[cfg(debug_assertions)]
+fn log_diagnostics(diagnostics: &[fml::Diagnostic]) {
+    for d in diagnostics {
+        match d.kind.severity() {
+            fml::Severity::Error => log::error!("{d}"),
+            fml::Severity::Warning => log::warn!("{d}"),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::path::{Path, PathBuf};
+
+use floem::reactive::{create_trigger, Trigger};
+
+/// Files in the app directory that views read, like images. Views that read assets are
+/// updated when files in the directory change.
+#[derive(Clone)]
+pub(crate) struct Assets {
+    dir: PathBuf,
+    changed: Trigger,
+}
+
+impl Assets {
+    pub fn new(dir: &Path) -> Self {
+        Assets {
+            dir: dir.to_path_buf(),
+            changed: create_trigger(),
+        }
+    }
+
+    /// Path relative to the app directory. Absolute paths are used as is.
+    pub fn path(&self, src: &str) -> PathBuf {
+        self.dir.join(src)
+    }
+
+    /// Reads the file. Subscribes to file changes when called in reactive context.
+    pub fn read(&self, src: &str) -> Option<Vec<u8>> {
+        self.changed.track();
+
+        let path = self.path(src);
+
+        std::fs::read(&path)
+            .map_err(|e| log::warn!("Failed to read asset {path:?}: {e}"))
+            .ok()
+    }
+
+    /// Makes views read their assets again
+    pub fn reload(&self) {
+        self.changed.notify();
+    }
+}
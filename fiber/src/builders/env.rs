@@ -0,0 +1,210 @@
+use std::path::Path;
+use std::rc::Rc;
+
+use floem::reactive::use_context;
+use fml::{AttributeValue, Expr, Node, NodeKey, TextElement, Value, VariableName, VariableType};
+
+use crate::StateCtx;
+
+/// Attribute given to a custom component. The value is resolved in the environment of the
+/// caller, so variables in props read the caller's loop items and props.
+#[derive(Clone)]
+pub(crate) struct Prop {
+    name: String,
+    value: AttributeValue<'static>,
+    env: Env,
+}
+
+impl Prop {
+    pub fn new(name: &str, value: AttributeValue<'static>, env: Env) -> Self {
+        Prop {
+            name: name.to_string(),
+            value,
+            env,
+        }
+    }
+}
+
+/// Children written inside a custom component tag, built in the environment of the caller
+pub(crate) struct Slots {
+    pub nodes: Vec<Node<'static>>,
+    pub env: Env,
+}
+
+impl Slots {
+    /// Children with matching `slot` attribute, or children without one for the default slot
+    pub fn nodes(&self, name: Option<&str>) -> Vec<&Node<'static>> {
+        self.nodes
+            .iter()
+            .filter(|n| {
+                let slot = match n {
+                    Node::Element(e) => e.get_attr("slot").map(ToString::to_string),
+                    _ => None,
+                };
+                slot.as_deref() == name
+            })
+            .collect()
+    }
+}
+
+/// Names visible to the element being built, like the item of a `for` loop or the props of a
+/// component. Lookups fall back to state variables when a name is not found.
+#[derive(Clone, Default)]
+pub(crate) struct Env {
+    locals: Rc<Vec<(String, Value)>>,
+    props: Rc<Vec<Prop>>,
+    slots: Option<Rc<Slots>>,
+    /// File the elements being built are from
+    file: Option<Rc<Path>>,
+    /// Path of the element in the document when its children are updated in place, see `live`
+    path: Option<Rc<[NodeKey]>>,
+    /// Path of the nearest element above that is updated in place, when this one is not
+    owner: Option<Rc<[NodeKey]>>,
+}
+
+impl Env {
+    pub fn with_file(file: &Path) -> Env {
+        Env {
+            file: Some(Rc::from(file)),
+            ..Env::default()
+        }
+    }
+
+    /// Environment for a component body. Caller locals are not visible inside components.
+    pub fn component(props: Vec<Prop>, slots: Slots, file: &Path) -> Env {
+        Env {
+            locals: Rc::default(),
+            props: Rc::new(props),
+            owner: slots.env.live_owner(),
+            slots: Some(Rc::new(slots)),
+            file: Some(Rc::from(file)),
+            path: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_path(&self, path: Vec<NodeKey>) -> Env {
+        Env {
+            path: Some(path.into()),
+            ..self.clone()
+        }
+    }
+
+    #[must_use]
+    pub fn without_path(&self) -> Env {
+        Env {
+            path: None,
+            owner: self.live_owner(),
+            ..self.clone()
+        }
+    }
+
+    pub fn path(&self) -> Option<&[NodeKey]> {
+        self.path.as_deref()
+    }
+
+    pub fn owner(&self) -> Option<&[NodeKey]> {
+        self.owner.as_deref()
+    }
+
+    fn live_owner(&self) -> Option<Rc<[NodeKey]>> {
+        self.path.clone().or_else(|| self.owner.clone())
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn slots(&self) -> Option<&Slots> {
+        self.slots.as_deref()
+    }
+
+    #[must_use]
+    pub fn with_local(&self, name: &str, value: Value) -> Env {
+        let mut locals = (*self.locals).clone();
+        locals.push((name.to_string(), value));
+
+        Env {
+            locals: Rc::new(locals),
+            props: self.props.clone(),
+            slots: self.slots.clone(),
+            file: self.file.clone(),
+            path: None,
+            owner: self.live_owner(),
+        }
+    }
+
+    /// Newest binding wins so nested loops can shadow outer names
+    pub fn local(&self, name: &str) -> Option<&Value> {
+        self.locals
+            .iter()
+            .rev()
+            .find_map(|(n, v)| (n == name).then_some(v))
+    }
+
+    pub fn prop(&self, name: &str) -> Option<Value> {
+        let prop = self.props.iter().find(|p| p.name == name)?;
+        prop.env.attr_value(&prop.value)
+    }
+
+    /// Resolves `name`, `name.field` or `prop:name`. State variables are read tracked, so
+    /// calling this inside a reactive closure subscribes to the variable.
+    pub fn resolve(&self, var: &VariableName) -> Option<Value> {
+        let (root, path) = match var.name.split_once('.') {
+            Some((root, path)) => (root, Some(path)),
+            None => (var.name.as_ref(), None),
+        };
+
+        let value = if let VariableType::Prop = var.kind {
+            self.prop(root)?
+        } else if let Some(local) = self.local(root) {
+            local.clone()
+        } else {
+            use_context::<StateCtx>().unwrap().get_value(root)?
+        };
+
+        match path {
+            Some(path) => value.get_path(path).cloned(),
+            None => Some(value),
+        }
+    }
+
+    /// Literal attribute values are used as is, variables are resolved
+    pub fn attr_value(&self, value: &AttributeValue) -> Option<Value> {
+        match value {
+            AttributeValue::String { value, .. } => Some(Value::String(value.to_string())),
+            AttributeValue::Integer { value, .. } => Some(Value::Integer(*value)),
+            AttributeValue::Float { value, .. } => Some(Value::Float(*value)),
+            AttributeValue::Boolean { value, .. } => Some(Value::Bool(*value)),
+            AttributeValue::Variable { name, .. } => self.resolve(name),
+            AttributeValue::Expression { expr, .. } => self.eval(expr),
+        }
+    }
+
+    /// Evaluates expression with variables resolved in this environment. Errors are logged.
+    pub fn eval(&self, expr: &Expr) -> Option<Value> {
+        expr.eval(&|var: &VariableName| self.resolve(var))
+            .map_err(|e| log::warn!("Failed to evaluate '{expr}': {e}"))
+            .ok()
+    }
+
+    /// Replaces variable references and expressions in text with their current values.
+    /// References are replaced by position, so escaped braces that look like one are kept.
+    pub fn interpolate(&self, text: &TextElement) -> String {
+        let mut content = String::with_capacity(text.content.len());
+        let mut pos = 0;
+
+        for var in &text.variable_refs {
+            let value = self
+                .eval(&var.expr)
+                .unwrap_or_else(|| Value::String(String::new()));
+
+            content.push_str(&text.content[pos..var.start]);
+            content.push_str(&value.to_string());
+            pos = var.end;
+        }
+
+        content.push_str(&text.content[pos..]);
+        content
+    }
+}
@@ -0,0 +1,217 @@
+use floem::event::{Event, EventListener};
+use floem::keyboard::{Key, Modifiers, NamedKey};
+use floem::reactive::use_context;
+use floem::views::Decorators;
+use floem::AnyView;
+use fml::{AttributeValue, Element, ElementKind, Expr, Value, VariableType};
+
+use super::env::Env;
+use crate::handler::{EventPayload, HandlerArgs};
+use crate::state::HandlerFn;
+use crate::StateCtx;
+
+/// Attributes that attach event handlers. Key events also accept a key filter, for example
+/// `onkeydown:Enter={submit}` or `onkeyup:ctrl-s={save}`.
+pub(crate) const EVENT_ATTRIBUTES: [&str; 11] = [
+    "onclick",
+    "ondoubleclick",
+    "onhover",
+    "onleave",
+    "onkeydown",
+    "onkeyup",
+    "onfocus",
+    "onblur",
+    "onchange",
+    "onsubmit",
+    "onscroll",
+];
+
+pub(crate) fn is_event_attribute(name: &str) -> bool {
+    let event = name.split_once(':').map_or(name, |(event, _)| event);
+    EVENT_ATTRIBUTES.contains(&event)
+}
+
+/// Handler named in an event attribute, with arguments evaluated when the event fires
+#[derive(Clone)]
+pub(crate) struct Handler {
+    name: String,
+    f: HandlerFn,
+    args: Vec<Expr>,
+    env: Env,
+}
+
+impl Handler {
+    pub fn call(&self, payload: EventPayload) {
+        let Some(args) = self
+            .args
+            .iter()
+            .map(|arg| self.env.eval(arg))
+            .collect::<Option<Vec<_>>>()
+        else {
+            log::warn!("Handler '{}' not called, invalid arguments", self.name);
+            return;
+        };
+
+        (self.f)(&HandlerArgs { args, payload });
+    }
+}
+
+/// Handler in the attribute, looked up from `State::fns`. The attribute can name the handler
+/// (`{save}`), call it with arguments (`{remove_item(int:row_id)}`) or take the name from a
+/// prop (`{prop:onremove}`).
+pub(crate) fn handler(elem: &Element<'static>, attr: &str, env: &Env) -> Option<Handler> {
+    let (name, args) = match elem.get_attr(attr)? {
+        AttributeValue::Variable { name, .. } if name.kind == VariableType::Prop => {
+            match env.prop(&name.name) {
+                Some(Value::String(name)) => (name, Vec::new()),
+                _ => {
+                    log::warn!("Prop '{name}' for '{attr}' is not a handler name");
+                    return None;
+                }
+            }
+        }
+        AttributeValue::Expression {
+            expr: Expr::Call(name, args),
+            ..
+        } => (name.clone(), args.clone()),
+        AttributeValue::Expression { expr, .. } => {
+            log::warn!("Invalid handler '{expr}' for '{attr}'");
+            return None;
+        }
+        value => (value.to_string(), Vec::new()),
+    };
+
+    let state = use_context::<StateCtx>().unwrap();
+
+    let Some(f) = state.get_fn(&name) else {
+        log::warn!("Handler '{name}' for '{attr}' not set");
+        return None;
+    };
+
+    Some(Handler {
+        name,
+        f,
+        args,
+        env: env.clone(),
+    })
+}
+
+/// Attaches handlers for all event attributes of the element. `onchange` and `onsubmit` are
+/// handled by the input builder, since they need the input text.
+pub(crate) fn apply(mut view: AnyView, elem: &Element<'static>, env: &Env) -> AnyView {
+    for attr in &elem.attributes {
+        let (event, filter) = match attr.name.split_once(':') {
+            Some((event, filter)) => (event, Some(filter)),
+            None => (attr.name.as_ref(), None),
+        };
+
+        if !EVENT_ATTRIBUTES.contains(&event) {
+            continue;
+        }
+
+        if matches!(event, "onchange" | "onsubmit") {
+            if !matches!(elem.kind, ElementKind::Input) {
+                log::warn!("'{event}' can be used only on input");
+            }
+            continue;
+        }
+
+        let Some(h) = handler(elem, &attr.name, env) else {
+            continue;
+        };
+
+        view = match (event, filter) {
+            ("onkeydown" | "onkeyup", Some(filter)) => {
+                let Some((key, modifiers)) = parse_key(filter) else {
+                    log::warn!("Unknown key filter '{filter}' in '{}'", attr.name);
+                    continue;
+                };
+
+                let action = move |e: &Event| h.call(EventPayload::from(e));
+
+                if event == "onkeydown" {
+                    view.on_key_down(key, modifiers, action)
+                } else {
+                    view.on_key_up(key, modifiers, action)
+                }
+            }
+            (_, Some(_)) => {
+                log::warn!("Only key events can have a filter: '{}'", attr.name);
+                continue;
+            }
+            ("onclick", None) => view.on_click_cont(move |e| h.call(EventPayload::from(e))),
+            ("ondoubleclick", None) => {
+                view.on_double_click_cont(move |e| h.call(EventPayload::from(e)))
+            }
+            (event, None) => {
+                let listener = match event {
+                    "onhover" => EventListener::PointerEnter,
+                    "onleave" => EventListener::PointerLeave,
+                    "onkeydown" => EventListener::KeyDown,
+                    "onkeyup" => EventListener::KeyUp,
+                    "onfocus" => EventListener::FocusGained,
+                    "onblur" => EventListener::FocusLost,
+                    "onscroll" => EventListener::PointerWheel,
+                    _ => unreachable!("Unhandled event attribute {event}"),
+                };
+
+                view.on_event_cont(listener, move |e| h.call(EventPayload::from(e)))
+            }
+        };
+    }
+
+    view
+}
+
+/// Parses key filter like `Enter`, `a` or `ctrl-shift-z`
+fn parse_key(filter: &str) -> Option<(Key, Modifiers)> {
+    let mut parts = filter.split('-').collect::<Vec<_>>();
+    let key = parts.pop()?;
+
+    let mut modifiers = Modifiers::empty();
+
+    for part in parts {
+        modifiers |= match part.to_lowercase().as_str() {
+            "ctrl" | "control" => Modifiers::CONTROL,
+            "shift" => Modifiers::SHIFT,
+            "alt" => Modifiers::ALT,
+            "meta" | "super" | "cmd" => Modifiers::SUPER,
+            _ => return None,
+        };
+    }
+
+    let named = match key.to_lowercase().as_str() {
+        "enter" => NamedKey::Enter,
+        "escape" | "esc" => NamedKey::Escape,
+        "tab" => NamedKey::Tab,
+        "space" => NamedKey::Space,
+        "backspace" => NamedKey::Backspace,
+        "delete" => NamedKey::Delete,
+        "up" | "arrowup" => NamedKey::ArrowUp,
+        "down" | "arrowdown" => NamedKey::ArrowDown,
+        "left" | "arrowleft" => NamedKey::ArrowLeft,
+        "right" | "arrowright" => NamedKey::ArrowRight,
+        "home" => NamedKey::Home,
+        "end" => NamedKey::End,
+        "pageup" => NamedKey::PageUp,
+        "pagedown" => NamedKey::PageDown,
+        "f1" => NamedKey::F1,
+        "f2" => NamedKey::F2,
+        "f3" => NamedKey::F3,
+        "f4" => NamedKey::F4,
+        "f5" => NamedKey::F5,
+        "f6" => NamedKey::F6,
+        "f7" => NamedKey::F7,
+        "f8" => NamedKey::F8,
+        "f9" => NamedKey::F9,
+        "f10" => NamedKey::F10,
+        "f11" => NamedKey::F11,
+        "f12" => NamedKey::F12,
+        _ if key.chars().count() == 1 => {
+            return Some((Key::Character(key.to_lowercase().into()), modifiers));
+        }
+        _ => return None,
+    };
+
+    Some((Key::Named(named), modifiers))
+}
@@ -0,0 +1,155 @@
+use floem::reactive::{create_memo, use_context};
+use floem::style::Style;
+use floem::unit::PxPctAuto;
+use floem::views::{dyn_container, empty, img, svg, Decorators};
+use floem::{AnyView, IntoView};
+use fml::Element;
+
+use super::env::Env;
+use crate::assets::Assets;
+use crate::theme::StyleCss;
+
+#[derive(Clone, PartialEq)]
+enum ImageData {
+    Raster(Vec<u8>),
+    Svg(String),
+}
+
+impl ImageData {
+    /// SVG is recognized from the content, so sources don't need an extension
+    fn new(bytes: Vec<u8>) -> ImageData {
+        match String::from_utf8(bytes) {
+            Ok(text) if text.trim_start().starts_with('<') => ImageData::Svg(text),
+            Ok(text) => ImageData::Raster(text.into_bytes()),
+            Err(e) => ImageData::Raster(e.into_bytes()),
+        }
+    }
+}
+
+/// How the image fills its box, set with the `fit` attribute
+#[derive(Clone, Copy)]
+enum Fit {
+    /// Stretched to the box
+    Fill,
+    /// Keeps the aspect ratio, the size not set is taken from the image
+    Contain,
+    /// Natural size of the image
+    None,
+}
+
+impl Fit {
+    fn from_elem(elem: &Element) -> Fit {
+        match elem.get_attr("fit").map(ToString::to_string).as_deref() {
+            None | Some("fill") => Fit::Fill,
+            Some("contain") => Fit::Contain,
+            Some("none") => Fit::None,
+            Some(other) => {
+                log::warn!("Image fit must be fill, contain or none, found '{other}'");
+                Fit::Fill
+            }
+        }
+    }
+
+    /// Size of the image in its container from the natural `(width, height)` of the image.
+    /// Width, height and classes of the element size the container.
+    fn style(self, size: Option<(f64, f64)>, s: Style) -> Style {
+        match (self, size) {
+            (Fit::Contain, Some((width, height))) if height > 0.0 => s
+                .width_full()
+                .max_height_full()
+                .aspect_ratio((width / height) as f32),
+            (Fit::None, Some((width, height))) => {
+                s.width(PxPctAuto::Px(width)).height(PxPctAuto::Px(height))
+            }
+            _ => s.size_full(),
+        }
+    }
+}
+
+/// Natural size of an SVG from the `width` and `height` of the `svg` element, or from its
+/// `viewBox` when those are missing or relative
+fn svg_size(content: &str) -> Option<(f64, f64)> {
+    let start = content.find("<svg")?;
+    let tag = &content[start..start + content[start..].find('>')?];
+
+    let length = |name| {
+        svg_attr(tag, name).and_then(|v| v.trim().trim_end_matches("px").parse::<f64>().ok())
+    };
+
+    if let (Some(width), Some(height)) = (length("width"), length("height")) {
+        return Some((width, height));
+    }
+
+    let view_box = svg_attr(tag, "viewBox")?
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|v| !v.is_empty())
+        .map(|v| v.parse::<f64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    match view_box.as_slice() {
+        [_, _, width, height] => Some((*width, *height)),
+        _ => None,
+    }
+}
+
+/// Value of a quoted attribute in a start tag
+fn svg_attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    tag.match_indices(name).find_map(|(i, _)| {
+        if !tag[..i].ends_with(char::is_whitespace) {
+            return None;
+        }
+
+        let value = tag[i + name.len()..]
+            .trim_start()
+            .strip_prefix('=')?
+            .trim_start();
+        let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        let value = &value[1..];
+
+        value.find(quote).map(|end| &value[..end])
+    })
+}
+
+/// Image from a PNG, JPEG or SVG file with `src="assets/logo.png"` or `src={str:logo}`. Paths
+/// are relative to the app directory, and the image is loaded again when the file changes.
+pub(super) fn build_image(elem: &Element<'static>, env: &Env) -> AnyView {
+    let Some(src) = elem.get_attr("src").cloned() else {
+        log::warn!("Image has no attribute 'src'");
+        return empty().into_any();
+    };
+
+    let assets = use_context::<Assets>().unwrap();
+    let fit = Fit::from_elem(elem);
+    let env = env.clone();
+
+    // Memo doesn't notify when the content stays the same, so unrelated file changes don't
+    // decode the image again
+    let data = create_memo(move |_| {
+        let src = env.attr_value(&src)?.to_string();
+        assets.read(&src).map(ImageData::new)
+    });
+
+    dyn_container(
+        move || data.get(),
+        move |data| match data {
+            Some(ImageData::Raster(bytes)) => {
+                let size = imagesize::blob_size(&bytes)
+                    .ok()
+                    .map(|size| (size.width as f64, size.height as f64));
+                let size = fit.style(size, Style::new());
+                img(move || bytes.clone())
+                    .style(move |s| s.apply(size.clone()))
+                    .into_any()
+            }
+            Some(ImageData::Svg(content)) => {
+                let size = fit.style(svg_size(&content), Style::new());
+                svg(move || content.clone())
+                    .style(move |s| s.apply(size.clone()))
+                    .into_any()
+            }
+            None => empty().into_any(),
+        },
+    )
+    .css("image")
+    .into_any()
+}
@@ -0,0 +1,216 @@
+use std::rc::Rc;
+
+use floem::event::EventListener;
+use floem::keyboard::{Key, Modifiers, NamedKey};
+use floem::reactive::{create_effect, untrack, use_context, RwSignal};
+use floem::views::{text_input, Decorators};
+use floem::{AnyView, IntoView};
+use fml::{AttributeValue, Element, VariableName, VariableType};
+
+use super::env::Env;
+use super::events;
+use crate::handler::EventPayload;
+use crate::theme::{StyleCss, Theme};
+use crate::StateCtx;
+
+/// State variable an input writes to
+struct Binding {
+    name: String,
+    kind: VariableType,
+    /// String variable that receives the validation error, set with `error={str:name}`
+    error_var: Option<String>,
+    state: StateCtx,
+}
+
+impl Binding {
+    fn new(elem: &Element<'static>, var: &VariableName) -> Option<Binding> {
+        let state = use_context::<StateCtx>().unwrap();
+
+        if var.name.contains('.') {
+            log::warn!("Input can't bind to nested value '{var}'");
+            return None;
+        }
+
+        let Some(value) = state.get_value(&var.name) else {
+            log::warn!("Input bound to unknown variable '{var}'");
+            return None;
+        };
+
+        // Values are written with the type of the stored value, so the state keeps its type
+        let kind = value.kind();
+        if var.kind != VariableType::Unknown && var.kind != kind {
+            log::warn!(
+                "Input bound to '{var}' as {} but the variable holds {kind}",
+                var.kind
+            );
+            return None;
+        }
+
+        if !matches!(
+            kind,
+            VariableType::String
+                | VariableType::Integer
+                | VariableType::Float
+                | VariableType::Boolean
+        ) {
+            log::warn!("Input can't bind to {kind} variable '{var}'");
+            return None;
+        }
+
+        let error_var = match elem.get_attr("error") {
+            Some(AttributeValue::Variable { name, .. }) => Some(name.name.to_string()),
+            _ => None,
+        };
+
+        if let Some(error_var) = &error_var {
+            if state.get_value(error_var).is_none() {
+                log::warn!("Input error variable '{error_var}' not declared");
+                state.insert::<String>(error_var, String::new());
+            }
+        }
+
+        Some(Binding {
+            name: var.name.to_string(),
+            kind,
+            error_var,
+            state,
+        })
+    }
+
+    /// Strings are written as is, other types only when the text parses
+    fn write(&self, text: &str) -> Result<(), String> {
+        let trimmed = text.trim();
+
+        match self.kind {
+            VariableType::Integer => {
+                let value = trimmed
+                    .parse::<i64>()
+                    .map_err(|_| format!("'{trimmed}' is not an integer"))?;
+                self.state.set::<i64>(&self.name, value);
+            }
+            VariableType::Float => {
+                let value = trimmed
+                    .parse::<f64>()
+                    .map_err(|_| format!("'{trimmed}' is not a number"))?;
+                self.state.set::<f64>(&self.name, value);
+            }
+            VariableType::Boolean => {
+                let value = trimmed
+                    .parse::<bool>()
+                    .map_err(|_| format!("'{trimmed}' is not true or false"))?;
+                self.state.set::<bool>(&self.name, value);
+            }
+            _ => self.state.set::<String>(&self.name, text.to_string()),
+        }
+
+        Ok(())
+    }
+
+    fn set_error(&self, error: Option<&str>) {
+        if let Some(error_var) = &self.error_var {
+            self.state
+                .set::<String>(error_var, error.unwrap_or_default().to_string());
+        }
+    }
+}
+
+/// Text input bound to a state variable with `value={str:name}` or `bind:value={name}`.
+/// String variables update on every edit. Numbers and bools are parsed when the input is
+/// committed with enter or by leaving the input, and invalid text sets the error state.
+pub(super) fn build_input(elem: &Element<'static>, env: &Env) -> AnyView {
+    let buffer = RwSignal::new(String::new());
+    let error = RwSignal::new(None::<String>);
+
+    let binding = match elem
+        .get_attr("bind:value")
+        .or_else(|| elem.get_attr("value"))
+    {
+        Some(AttributeValue::Variable { name, .. }) => Binding::new(elem, name).map(Rc::new),
+        Some(AttributeValue::String { value, .. }) if elem.get_attr("bind:value").is_some() => {
+            Binding::new(elem, &VariableName::from(value.as_ref())).map(Rc::new)
+        }
+        Some(value) => {
+            let text = env.attr_value(value).map(|v| v.to_string());
+            buffer.set(text.unwrap_or_default());
+            None
+        }
+        None => None,
+    };
+
+    if let Some(binding) = binding.clone() {
+        // State to input. Equal text is not written back, so typing doesn't move the cursor.
+        create_effect(move |_| {
+            let text = binding
+                .state
+                .get_value(&binding.name)
+                .map(|v| v.to_string())
+                .unwrap_or_default();
+
+            if buffer.with_untracked(|b| *b != text) {
+                buffer.set(text);
+            }
+        });
+    }
+
+    if let Some(binding) = binding.clone().filter(|b| b.kind == VariableType::String) {
+        // Input to state on every edit
+        create_effect(move |prev: Option<()>| {
+            let text = buffer.get();
+            if prev.is_some() {
+                untrack(|| binding.write(&text)).ok();
+            }
+        });
+    }
+
+    if let Some(onchange) = events::handler(elem, "onchange", env) {
+        // First run only subscribes to the buffer. The handler is untracked so reads inside it
+        // don't trigger the effect.
+        create_effect(move |prev: Option<()>| {
+            let text = buffer.get();
+            if prev.is_some() {
+                untrack(|| onchange.call(EventPayload::Text(text)));
+            }
+        });
+    }
+
+    let commit = Rc::new(move || {
+        let Some(binding) = binding.as_ref().filter(|b| b.kind != VariableType::String) else {
+            return;
+        };
+
+        match binding.write(&buffer.get_untracked()) {
+            Ok(()) => {
+                error.set(None);
+                binding.set_error(None);
+            }
+            Err(e) => {
+                log::debug!("Invalid input for '{}': {e}", binding.name);
+                binding.set_error(Some(&e));
+                error.set(Some(e));
+            }
+        }
+    });
+
+    let onsubmit = events::handler(elem, "onsubmit", env);
+    let submit_commit = commit.clone();
+
+    let theme = use_context::<RwSignal<Theme>>().unwrap();
+
+    text_input(buffer)
+        .on_key_down(Key::Named(NamedKey::Enter), Modifiers::empty(), move |_| {
+            submit_commit();
+            if let Some(onsubmit) = &onsubmit {
+                onsubmit.call(EventPayload::Text(buffer.get_untracked()));
+            }
+        })
+        .on_event_cont(EventListener::FocusLost, move |_| commit())
+        .css("input")
+        .style(move |s| {
+            if error.with(Option::is_some) {
+                theme.get().apply_classes(s, &["input-error"])
+            } else {
+                s
+            }
+        })
+        .into_any()
+}
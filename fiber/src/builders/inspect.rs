@@ -0,0 +1,61 @@
+use floem::views::Decorators;
+use floem::AnyView;
+use fml::Element;
+
+use super::env::Env;
+
+/// Editor command set with `App::editor` or `FIBER_EDITOR`. `{file}`, `{line}` and `{col}` are
+/// replaced with the location, like in `code --goto {file}:{line}:{col}`.
+#[derive(Clone)]
+pub(crate) struct EditorCommand(pub String);
+
+/// Names the view after the element and its source location, so the inspector opened with F11
+/// shows where the selected view is defined. Alt-click opens the location in the editor.
+#[cfg(debug_assertions)]
+pub(super) fn apply(view: AnyView, elem: &Element<'static>, env: &Env) -> AnyView {
+    use floem::event::{Event, EventListener, EventPropagation};
+    use floem::reactive::use_context;
+
+    let file = env
+        .file()
+        .map_or_else(|| "<unknown>".to_string(), |f| f.display().to_string());
+    let (line, col) = (elem.span.line, elem.span.col);
+    let editor = use_context::<EditorCommand>();
+
+    view.debug_name(format!("<{}> {file}:{line}:{col}", elem.kind.name()))
+        .on_event(EventListener::PointerDown, move |e| match e {
+            Event::PointerDown(p) if p.modifiers.alt_key() => {
+                open(editor.as_ref(), &file, line, col);
+                EventPropagation::Stop
+            }
+            _ => EventPropagation::Continue,
+        })
+}
+
+#[cfg(not(debug_assertions))]
+pub(super) fn apply(view: AnyView, elem: &Element<'static>, _env: &Env) -> AnyView {
+    view.debug_name(elem.kind.name())
+}
+
+#[cfg(debug_assertions)]
+fn open(editor: Option<&EditorCommand>, file: &str, line: usize, col: usize) {
+    let Some(EditorCommand(command)) = editor else {
+        log::info!("Element is defined at {file}:{line}:{col}. Set editor command to open it.");
+        return;
+    };
+
+    let mut args = command.split_whitespace().map(|arg| {
+        arg.replace("{file}", file)
+            .replace("{line}", &line.to_string())
+            .replace("{col}", &col.to_string())
+    });
+
+    let Some(program) = args.next() else {
+        log::warn!("Editor command is empty");
+        return;
+    };
+
+    if let Err(e) = std::process::Command::new(&program).args(args).spawn() {
+        log::error!("Failed to run editor '{program}': {e}");
+    }
+}
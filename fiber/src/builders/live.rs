@@ -0,0 +1,535 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use floem::reactive::{use_context, RwSignal};
+use floem::views::dyn_stack;
+use floem::{AnyView, IntoView};
+use fml::{Change, Element, ElementKind, Node, NodeKey};
+
+use super::env::Env;
+use super::{component, component_def, element_to_anyview, is_fragment, node};
+use crate::observer::ComponentDef;
+
+/// Child of a container in the document. Children of fragments, and of components with a
+/// fragment root, are children of the container too, so the key is the path from the
+/// container. The view is built again when the revision changes.
+#[derive(Clone)]
+struct LiveChild {
+    key: Vec<NodeKey>,
+    revision: u64,
+    node: Node<'static>,
+    env: Env,
+}
+
+#[derive(Clone)]
+struct Container {
+    children: RwSignal<Vec<LiveChild>>,
+    env: Env,
+}
+
+/// Children of the containers in the main document and in the components built in it, by
+/// path. Paths continue into a component after the `#component` key of its instance. On
+/// reload the new sources are diffed against the previous ones and only the children that
+/// changed are built again, so focus, scroll and input contents of other views are kept.
+#[derive(Clone, Default)]
+pub(crate) struct LiveTree(Rc<RefCell<Inner>>);
+
+#[derive(Default)]
+struct Inner {
+    root: Option<Node<'static>>,
+    containers: HashMap<Vec<NodeKey>, Container>,
+    /// Definitions the components were built from by the path of their root, `None` for
+    /// components that failed to build
+    components: HashMap<Vec<NodeKey>, Option<Rc<ComponentDef>>>,
+    /// Definitions of the components built outside of containers, by the path of the element
+    /// they are built with and their name
+    outside: HashMap<(Vec<NodeKey>, String), Option<Rc<ComponentDef>>>,
+    revision: u64,
+}
+
+impl Inner {
+    fn next_revision(&mut self) -> u64 {
+        self.revision += 1;
+        self.revision
+    }
+}
+
+/// Change to apply to the views, by path in the document
+enum Update {
+    Replace(Vec<NodeKey>),
+    Children(Vec<NodeKey>),
+}
+
+impl Update {
+    fn new(change: Change, prefix: &[NodeKey]) -> Update {
+        match change {
+            Change::Replace { path, .. } => Update::Replace([prefix, &path].concat()),
+            Change::Children { path, .. } => Update::Children([prefix, &path].concat()),
+        }
+    }
+}
+
+impl LiveTree {
+    /// Applies the changes of the new main document and of the components to the views.
+    /// Returns false if the whole document has to be built again.
+    pub fn update(&self, source: &str) -> bool {
+        let Ok(root) = fml::parse(source) else {
+            return false;
+        };
+        let root = root.into_owned();
+
+        let mut inner = self.0.borrow_mut();
+
+        let Some(old) = inner.root.take() else {
+            return false;
+        };
+
+        let mut updater = Updater {
+            inner: &mut *inner,
+            root: &root,
+            updates: HashMap::new(),
+            rebuilt: Vec::new(),
+        };
+
+        for change in fml::diff(&old, &root) {
+            if !updater.apply(Update::new(change, &[])) {
+                return false;
+            }
+        }
+
+        // Components are compared after the main document, so instances it removed or built
+        // again are skipped
+        let bodies = updater.inner.components.keys().cloned().collect::<Vec<_>>();
+        for body in bodies {
+            for change in updater.component_changes(&body) {
+                if !updater.apply(change) {
+                    return false;
+                }
+            }
+        }
+
+        let outside = updater
+            .inner
+            .outside
+            .iter()
+            .filter(|((_, name), def)| changed(name, def.as_ref()))
+            .map(|((owner, _), _)| owner.clone())
+            .collect::<Vec<_>>();
+        for owner in outside {
+            if !updater.apply(Update::Replace(owner)) {
+                return false;
+            }
+        }
+
+        let updates = updater.updates;
+
+        let signals = updates
+            .into_iter()
+            .filter_map(|(path, children)| Some((inner.containers.get(&path)?.children, children)))
+            .collect::<Vec<_>>();
+
+        inner.root = Some(root);
+
+        // Building the views registers new containers
+        drop(inner);
+
+        for (signal, children) in signals {
+            signal.set(children);
+        }
+
+        true
+    }
+}
+
+struct Updater<'a> {
+    inner: &'a mut Inner,
+    root: &'a Node<'static>,
+    /// New children of the containers that changed
+    updates: HashMap<Vec<NodeKey>, Vec<LiveChild>>,
+    /// Paths of the children built again, changes below them are already applied
+    rebuilt: Vec<Vec<NodeKey>>,
+}
+
+impl Updater<'_> {
+    fn apply(&mut self, update: Update) -> bool {
+        let (path, replace) = match update {
+            Update::Replace(path) => (path, true),
+            Update::Children(path) => (path, false),
+        };
+
+        if self.rebuilt.iter().any(|p| path.starts_with(p)) {
+            return true;
+        }
+
+        if !replace && self.inner.containers.contains_key(&path) {
+            return self.relist(&path, None);
+        }
+
+        // Views are built again from the nearest child of a container. The root is built with
+        // the whole document.
+        let Some(len) = (0..path.len())
+            .rev()
+            .find(|len| self.inner.containers.contains_key(&path[..*len]))
+        else {
+            return false;
+        };
+
+        let (container, relative) = path.split_at(len);
+
+        let mut children = self.pending(container);
+        let Some(i) = children.iter().position(|c| relative.starts_with(&c.key)) else {
+            // A fragment, its children are listed again
+            self.updates.insert(container.to_vec(), children);
+            return self.relist(container, replace.then_some(relative));
+        };
+
+        let target = [container, &children[i].key].concat();
+        let Some(node) = node_at(self.root, &target) else {
+            return false;
+        };
+
+        // A child that became a fragment is replaced with its children
+        if replace && target == path && is_fragment(&node) {
+            self.updates.insert(container.to_vec(), children);
+            return self.relist(container, Some(relative));
+        }
+
+        children[i].revision = self.inner.next_revision();
+        children[i].node = node;
+        self.updates.insert(container.to_vec(), children);
+
+        forget(self.inner, &mut self.updates, &target);
+        self.rebuilt.push(target);
+
+        true
+    }
+
+    /// Lists the children of the container again from the new sources. Children keep their
+    /// views unless they are new or below `fresh`.
+    fn relist(&mut self, container: &[NodeKey], fresh: Option<&[NodeKey]>) -> bool {
+        let Some(Node::Element(elem)) = node_at(self.root, container) else {
+            return false;
+        };
+        let Some(env) = self.inner.containers.get(container).map(|c| c.env.clone()) else {
+            return false;
+        };
+
+        let current = self.pending(container);
+
+        if let Some(fresh) = fresh {
+            let path = [container, fresh].concat();
+            forget(self.inner, &mut self.updates, &path);
+            self.rebuilt.push(path);
+        }
+
+        let mut next = Vec::new();
+        let mut components = Vec::new();
+        flatten(
+            &elem.children,
+            &env,
+            &mut Vec::new(),
+            &mut next,
+            &mut components,
+        );
+
+        for removed in current
+            .iter()
+            .filter(|c| next.iter().all(|n| n.key != c.key))
+        {
+            forget(
+                self.inner,
+                &mut self.updates,
+                &[container, &removed.key].concat(),
+            );
+        }
+
+        for child in &mut next {
+            child.revision = current
+                .iter()
+                .find(|c| c.key == child.key && !fresh.is_some_and(|f| c.key.starts_with(f)))
+                .map_or_else(|| self.inner.next_revision(), |c| c.revision);
+        }
+
+        // Kept children were built from the previous definitions, those are compared later
+        for (body, def) in components {
+            self.inner
+                .components
+                .entry([container, &body].concat())
+                .or_insert(Some(def));
+        }
+
+        self.updates.insert(container.to_vec(), next);
+
+        true
+    }
+
+    /// Changes in the definition of a component built at the path, as changes in the document
+    fn component_changes(&mut self, body: &[NodeKey]) -> Vec<Update> {
+        let Some(old) = self.inner.components.get(body).cloned() else {
+            return Vec::new();
+        };
+        if self.rebuilt.iter().any(|p| body.starts_with(p)) {
+            return Vec::new();
+        }
+
+        let instance = &body[..body.len() - 1];
+        let Some(Node::Element(Element {
+            kind: ElementKind::Custom(name),
+            ..
+        })) = node_at(self.root, instance)
+        else {
+            return Vec::new();
+        };
+
+        let new = component_def(&name).and_then(Result::ok);
+        self.inner.components.insert(body.to_vec(), new.clone());
+
+        match (old, new) {
+            (Some(old), Some(new)) if Rc::ptr_eq(&old, &new) || old == new => Vec::new(),
+            (Some(old), Some(new))
+                if old.props == new.props && is_fragment_root(&old) == is_fragment_root(&new) =>
+            {
+                fml::diff(&old.root, &new.root)
+                    .into_iter()
+                    .map(|change| Update::new(change, body))
+                    .collect()
+            }
+            _ => vec![Update::Replace(instance.to_vec())],
+        }
+    }
+
+    /// Children of the container with the updates made so far
+    fn pending(&mut self, path: &[NodeKey]) -> Vec<LiveChild> {
+        self.updates.remove(path).unwrap_or_else(|| {
+            self.inner
+                .containers
+                .get(path)
+                .map(|c| c.children.get_untracked())
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// True if the definition of the component differs from the one it was built from
+fn changed(name: &str, def: Option<&Rc<ComponentDef>>) -> bool {
+    match (def, component_def(name).and_then(Result::ok)) {
+        (Some(old), Some(new)) => !Rc::ptr_eq(old, &new) && **old != *new,
+        _ => true,
+    }
+}
+
+fn is_fragment_root(def: &ComponentDef) -> bool {
+    matches!(
+        &def.root,
+        Node::Element(Element {
+            kind: ElementKind::Fragment,
+            ..
+        })
+    )
+}
+
+fn component_key() -> NodeKey {
+    NodeKey::Position("#component".to_string(), 0)
+}
+
+/// Node at the path in the document. After the `#component` key of an instance the path
+/// continues in the root of the component.
+fn node_at(root: &Node<'static>, path: &[NodeKey]) -> Option<Node<'static>> {
+    let Some(i) = path.iter().position(|key| *key == component_key()) else {
+        return fml::node_at(root, path).cloned();
+    };
+
+    let Some(Node::Element(Element {
+        kind: ElementKind::Custom(name),
+        ..
+    })) = fml::node_at(root, &path[..i])
+    else {
+        return None;
+    };
+
+    let def = component_def(name)?.ok()?;
+    node_at(&def.root, &path[i + 1..])
+}
+
+/// Drops the containers and components at and below the path, their views are removed or
+/// built again
+fn forget(
+    inner: &mut Inner,
+    updates: &mut HashMap<Vec<NodeKey>, Vec<LiveChild>>,
+    path: &[NodeKey],
+) {
+    inner.containers.retain(|p, _| !p.starts_with(path));
+    inner.components.retain(|p, _| !p.starts_with(path));
+    inner.outside.retain(|(p, _), _| !p.starts_with(path));
+    updates.retain(|p, _| !p.starts_with(path));
+}
+
+/// Children of the container with the children of fragments in place of the fragments.
+/// Components with a fragment root are collected with the path of their root.
+fn flatten(
+    nodes: &[Node<'static>],
+    env: &Env,
+    prefix: &mut Vec<NodeKey>,
+    children: &mut Vec<LiveChild>,
+    components: &mut Vec<(Vec<NodeKey>, Rc<ComponentDef>)>,
+) {
+    for (key, node) in fml::keys(nodes).into_iter().zip(nodes) {
+        prefix.push(key);
+
+        let flattened = match node {
+            Node::Element(elem) if is_fragment(node) => match &elem.kind {
+                ElementKind::Custom(name) => match component(name, elem, env) {
+                    Ok((def, component_env)) => {
+                        super::warn_fragment_attributes(name, elem);
+
+                        prefix.push(component_key());
+                        components.push((prefix.clone(), def.clone()));
+
+                        if let Node::Element(root) = &def.root {
+                            flatten(&root.children, &component_env, prefix, children, components);
+                        }
+
+                        prefix.pop();
+                        true
+                    }
+                    // Built as a child to show the error
+                    Err(_) => false,
+                },
+                _ => {
+                    flatten(&elem.children, env, prefix, children, components);
+                    true
+                }
+            },
+            _ => false,
+        };
+
+        if !flattened {
+            children.push(LiveChild {
+                key: prefix.clone(),
+                revision: 0,
+                node: node.clone(),
+                env: env.clone(),
+            });
+        }
+
+        prefix.pop();
+    }
+}
+
+/// Builds the main document so that it can be updated with `LiveTree::update`. Without a
+/// `LiveTree` in context the document is built as is.
+pub(super) fn root(root: &Node<'static>, env: &Env) -> AnyView {
+    let Some(live) = use_context::<LiveTree>() else {
+        return node(root, env);
+    };
+
+    {
+        let mut inner = live.0.borrow_mut();
+        inner.root = Some(root.clone());
+        inner.containers.clear();
+        inner.components.clear();
+        inner.outside.clear();
+    }
+
+    live_node(root, &env.with_path(Vec::new()))
+}
+
+/// Children of a container in the document, or `None` if the element is not in it
+pub(super) fn children(elem: &Element<'static>, env: &Env) -> Option<AnyView> {
+    let path = env.path()?.to_vec();
+    let live = use_context::<LiveTree>()?;
+
+    let env = env.without_path();
+
+    let mut children = Vec::new();
+    let mut components = Vec::new();
+    flatten(
+        &elem.children,
+        &env,
+        &mut Vec::new(),
+        &mut children,
+        &mut components,
+    );
+
+    let signal = RwSignal::new(children);
+
+    {
+        let mut inner = live.0.borrow_mut();
+        inner.containers.insert(
+            path.clone(),
+            Container {
+                children: signal,
+                env,
+            },
+        );
+        for (body, def) in components {
+            inner
+                .components
+                .insert([path.as_slice(), &body].concat(), Some(def));
+        }
+    }
+
+    let view = dyn_stack(
+        move || signal.get(),
+        |child| (child.key.clone(), child.revision),
+        move |child| {
+            live_node(
+                &child.node,
+                &child.env.with_path([path.as_slice(), &child.key].concat()),
+            )
+        },
+    );
+
+    Some(view.into_any())
+}
+
+/// Builds a component instance. In the document the root of the component is built in place
+/// of the instance, so changes in the component are applied like changes in the document.
+pub(super) fn instance(
+    name: &str,
+    def: &Rc<ComponentDef>,
+    component_env: &Env,
+    env: &Env,
+) -> AnyView {
+    let (Some(path), Some(live)) = (env.path(), use_context::<LiveTree>()) else {
+        track(name, Some(def.clone()), env);
+        return node(&def.root, component_env);
+    };
+
+    let body = [path, &[component_key()]].concat();
+    live.0
+        .borrow_mut()
+        .components
+        .insert(body.clone(), Some(def.clone()));
+
+    live_node(&def.root, &component_env.with_path(body))
+}
+
+/// Keeps the definition a component was built from. Components outside of containers are
+/// built again with the nearest element that is updated in place when the definition changes.
+pub(super) fn track(name: &str, def: Option<Rc<ComponentDef>>, env: &Env) {
+    let Some(live) = use_context::<LiveTree>() else {
+        return;
+    };
+    let mut inner = live.0.borrow_mut();
+
+    match env.path() {
+        Some(path) => {
+            inner
+                .components
+                .insert([path, &[component_key()]].concat(), def);
+        }
+        None => {
+            let owner = env.owner().unwrap_or_default().to_vec();
+            inner.outside.insert((owner, name.to_string()), def);
+        }
+    }
+}
+
+fn live_node(node: &Node<'static>, env: &Env) -> AnyView {
+    match node {
+        Node::Element(elem) => element_to_anyview(elem, env),
+        other => node(other, env),
+    }
+}
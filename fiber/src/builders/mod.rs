@@ -0,0 +1,682 @@
+mod env;
+mod events;
+mod image;
+mod input;
+mod inspect;
+mod live;
+
+use std::path::Path;
+use std::rc::Rc;
+
+use env::{Env, Prop, Slots};
+use floem::keyboard::{Key, Modifiers, NamedKey};
+use floem::peniko::Color;
+use floem::reactive::{use_context, RwSignal};
+use floem::style::Style;
+use floem::unit::{PxPct, PxPctAuto};
+use floem::views::{
+    button, clip, container, dyn_container, dyn_view, empty, label, stack_from_iter, text,
+    v_stack_from_iter, Decorators,
+};
+use floem::{AnyView, IntoView, View};
+use fml::{
+    Attribute, AttributeValue, Element, ElementKind, Node, ParseError, Value, VariableName,
+    VariableType,
+};
+
+use crate::observer::{ComponentDef, SourceObserver};
+use crate::state::Viewable;
+use crate::theme::parser::{parse_color, parse_px_pct, parse_pxpctauto};
+use crate::theme::{StyleCss, Theme};
+use crate::StateCtx;
+
+pub(crate) use inspect::EditorCommand;
+pub(crate) use live::LiveTree;
+
+pub(crate) fn source(source: &str, path: &Path) -> impl View {
+    let start = std::time::SystemTime::now();
+
+    if let Some(state) = use_context::<StateCtx>() {
+        state.clear_view_ids();
+    }
+
+    let view = match fml::parse(source) {
+        Ok(root_node) => live::root(&root_node.into_owned(), &Env::with_file(path)),
+        Err(errors) => parse_errors(&errors),
+    }
+    .style(Style::size_full)
+    .keyboard_navigatable();
+
+    let end = start.elapsed().unwrap();
+    log::info!("View built in {}ms", end.as_millis());
+
+    let id = view.id();
+    view.on_key_up(Key::Named(NamedKey::F11), Modifiers::empty(), move |_| {
+        id.inspect()
+    })
+}
+
+fn node(node: &Node<'static>, env: &Env) -> AnyView {
+    // Only children of containers are updated in place, others are built with their parent
+    if env.path().is_some() {
+        return self::node(node, &env.without_path());
+    }
+
+    match node {
+        Node::Element(e) => element_to_anyview(e, env),
+        Node::Text(t) => text(&t.content).into_any(),
+        Node::Comment(_) | Node::BlockComment(_) => empty().into_any(),
+    }
+}
+
+// TODO Too many lines
+fn element_to_anyview(elem: &Element<'static>, env: &Env) -> AnyView {
+    // Variables and expressions are resolved in the style closure, so the style follows them
+    let (dynamic_attrs, static_attrs): (Vec<_>, Vec<_>) = elem.attributes.iter().partition(|a| {
+        matches!(
+            a.value,
+            AttributeValue::Variable { .. } | AttributeValue::Expression { .. }
+        )
+    });
+    let style_attrs = static_attrs
+        .into_iter()
+        .fold(Style::new(), |s, attr| attr_to_style(attr, env, s));
+    let dynamic_attrs = dynamic_attrs
+        .into_iter()
+        .filter(|a| STYLE_ATTRIBUTES.contains(&a.name.as_ref()))
+        .cloned()
+        .collect::<Vec<_>>();
+    let style_env = env.clone();
+
+    let view = match &elem.kind {
+        ElementKind::Root => build_root(elem, env),
+        ElementKind::Box => build_box(elem, env),
+        ElementKind::Clip => build_clip(elem, env),
+        ElementKind::Empty => build_empty(elem),
+        ElementKind::Label => build_label(elem, env),
+        ElementKind::Button => build_button(elem),
+        ElementKind::HStack => build_hstack(elem, env),
+        ElementKind::VStack => build_vstack(elem, env),
+        ElementKind::Input => input::build_input(elem, env),
+        ElementKind::Image => image::build_image(elem, env),
+        ElementKind::List => build_list(elem, env),
+        ElementKind::If => build_if(elem, env),
+        ElementKind::For => build_for(elem, env),
+        ElementKind::Slot => build_slot(elem, env),
+        ElementKind::Props => {
+            log::warn!("Props can be declared only in the root of a component");
+            empty().into_any()
+        }
+        // Imports are resolved when sources are loaded
+        ElementKind::Import => empty().into_any(),
+        ElementKind::Component => {
+            log::warn!("Components can be defined only in imported files");
+            empty().into_any()
+        }
+        ElementKind::Custom(name) => build_custom(name, elem, env),
+        // Fragments are flattened into containers, alone they are laid out like a vstack
+        ElementKind::Fragment => container_children(elem, env)
+            .style(|s| s.flex_col())
+            .into_any(),
+        // Else is taken apart by its `if`
+        ElementKind::Else => {
+            log::warn!("Else can be used only as the last child of if");
+            empty().into_any()
+        }
+    }
+    .style(move |s| {
+        dynamic_attrs
+            .iter()
+            .fold(s.apply(style_attrs.clone()), |s, attr| {
+                attr_to_style(attr, &style_env, s)
+            })
+    });
+
+    let view = events::apply(view, elem, env);
+    let view = inspect::apply(view, elem, env);
+
+    if let Some(AttributeValue::String { value, .. }) = elem.get_attr("id") {
+        use_context::<StateCtx>()
+            .unwrap()
+            .set_view_id(value, view.id());
+    }
+
+    view
+}
+
+/// Attributes handled by `attr_to_style`, and `slot`, `key` and `id` that are not props
+const STYLE_ATTRIBUTES: [&str; 10] = [
+    "class", "gap", "width", "height", "margin", "padding", "color", "slot", "key", "id",
+];
+
+fn attr_to_style<'a>(attr: &'a Attribute<'a>, env: &Env, s: Style) -> Style {
+    match attr.name.as_ref() {
+        "class" => {
+            if let AttributeValue::String { value, .. } = &attr.value {
+                let theme = use_context::<RwSignal<Theme>>().unwrap();
+                let classes = value.split_whitespace().collect::<Vec<_>>();
+                theme.get().apply_classes(s, &classes)
+            } else {
+                s
+            }
+        }
+        "gap" => s.gap(attr_value_to_px_pct(&attr.value, env)),
+        "width" => s.width(attr_value_to_px_pct_auto(&attr.value, env)),
+        "height" => s.height(attr_value_to_px_pct_auto(&attr.value, env)),
+        "margin" => s.margin(attr_value_to_px_pct_auto(&attr.value, env)),
+        "padding" => s.padding(attr_value_to_px_pct(&attr.value, env)),
+        "color" => s.color(attr_value_to_color(&attr.value, env)),
+        _ => s,
+    }
+}
+
+fn build_root(elem: &Element<'static>, env: &Env) -> AnyView {
+    let children = container_children(elem, env);
+    container(children)
+        .style(Style::size_full)
+        .css("root")
+        .into_any()
+}
+
+fn build_box(elem: &Element<'static>, env: &Env) -> AnyView {
+    let children = container_children(elem, env);
+    container(children).css("box").into_any()
+}
+
+/// Box that clips children to its bounds
+fn build_clip(elem: &Element<'static>, env: &Env) -> AnyView {
+    let children = container_children(elem, env);
+    clip(children).css("clip").into_any()
+}
+
+/// Spacer sized with width and height attributes or classes
+fn build_empty(elem: &Element<'static>) -> AnyView {
+    if !elem.children.is_empty() {
+        log::warn!("Empty can't have children");
+    }
+
+    empty().css("empty").into_any()
+}
+
+fn build_label(elem: &Element<'static>, env: &Env) -> AnyView {
+    if elem.children.is_empty() {
+        return text("").into_any();
+    }
+
+    if elem.children.iter().any(|e| matches!(e, Node::Element(_))) {
+        return text("Label can have only one text element as child").into_any();
+    }
+
+    let Some(Node::Text(t)) = elem.children.first() else {
+        return text("Label can have only one text element as child").into_any();
+    };
+
+    if t.variable_refs.is_empty() {
+        return text(&t.content).into_any();
+    }
+
+    let t = t.clone();
+    let env = env.clone();
+
+    label(move || env.interpolate(&t)).into_any()
+}
+
+fn build_button(elem: &Element<'static>) -> AnyView {
+    let mut button = if let Some(Node::Text(t)) = elem.children.first() {
+        let val = t.content.to_string();
+        button(move || val.clone()).into_any()
+    } else {
+        let id = elem.id;
+        button(move || format!("Button {id}")).into_any()
+    };
+
+    // Flips a bool variable on click
+    if let Some(AttributeValue::Variable { name, .. }) = elem.get_attr("toggle") {
+        let state = use_context::<StateCtx>().unwrap();
+        let name = name.name.to_string();
+
+        if !matches!(state.get_value(&name), Some(Value::Bool(_))) {
+            log::warn!("Button toggle variable '{name}' is not a bool");
+        }
+
+        button = button.on_click_cont(move |_| state.update::<bool>(&name, |v| *v = !*v));
+    }
+
+    button.css("button")
+}
+
+fn build_hstack(elem: &Element<'static>, env: &Env) -> AnyView {
+    container_children(elem, env)
+        .style(|s| s.flex_row())
+        .css("hstack")
+        .into_any()
+}
+
+fn build_vstack(elem: &Element<'static>, env: &Env) -> AnyView {
+    container_children(elem, env)
+        .style(|s| s.flex_col())
+        .css("vstack")
+        .into_any()
+}
+
+fn build_list(elem: &Element<'static>, env: &Env) -> AnyView {
+    let Some(attr) = elem.attributes.iter().find(|a| a.name == "items") else {
+        log::warn!("List has no attribute 'items'");
+        return container(empty()).into_any();
+    };
+
+    let Attribute {
+        name: _,
+        value:
+            AttributeValue::Variable {
+                name:
+                    VariableName {
+                        kind: VariableType::Unknown,
+                        name: varname,
+                    },
+                ..
+            },
+        ..
+    } = attr
+    else {
+        log::warn!("List attribute 'items' must be variable");
+        return container(empty()).into_any();
+    };
+
+    let state = use_context::<StateCtx>().unwrap();
+
+    // let Some(items_sig) = state.get_view(varname) else {
+    //     log::warn!("State has no variable '{varname}'");
+    //     return container(empty()).into_any();
+    // };
+
+    let Some(items_sig) = state.get::<Vec<Box<dyn Viewable>>>(varname) else {
+        log::warn!("State has no variable '{varname}'");
+        return container(empty()).into_any();
+    };
+
+    let style_attrs = elem
+        .attributes
+        .iter()
+        .fold(Style::new(), |s, attr| attr_to_style(attr, env, s));
+
+    dyn_view(move || {
+        let style_attrs = style_attrs.clone();
+        let items = items_sig.with(|s| {
+            if let Some(v) = (*s).downcast_ref::<Vec<Box<dyn Viewable>>>() {
+                v.iter().map(|f| f.into_anyview()).collect::<Vec<_>>()
+            } else {
+                log::error!("Cast to Viewable failed in build_list");
+                Vec::new()
+            }
+        });
+        stack_from_iter(items).style(move |s| s.apply(style_attrs.clone()))
+    })
+    .into_any()
+}
+
+fn build_if(elem: &Element<'static>, env: &Env) -> AnyView {
+    let Some(cond @ (AttributeValue::Variable { .. } | AttributeValue::Expression { .. })) =
+        elem.get_attr("cond")
+    else {
+        log::warn!("If attribute 'cond' must be variable or expression");
+        return empty().into_any();
+    };
+
+    if env.attr_value(cond).is_none() {
+        log::warn!("If condition '{cond}' could not be resolved");
+    }
+
+    let cond = cond.clone();
+    let (then_branch, else_branch) = elem.branches();
+    let (then_branch, else_branch) = (then_branch.to_vec(), else_branch.to_vec());
+    let env = env.clone();
+    let branch_env = env.clone();
+
+    dyn_container(
+        move || env.attr_value(&cond).is_some_and(|v| v.is_truthy()),
+        move |cond| {
+            let branch = if cond { &then_branch } else { &else_branch };
+            v_stack_from_iter(branch.iter().flat_map(|n| views(n, &branch_env))).into_any()
+        },
+    )
+    .css("if")
+    .into_any()
+}
+
+fn build_for(elem: &Element<'static>, env: &Env) -> AnyView {
+    let Some(each @ (AttributeValue::Variable { .. } | AttributeValue::Expression { .. })) =
+        elem.get_attr("each")
+    else {
+        log::warn!("For attribute 'each' must be variable or expression");
+        return empty().into_any();
+    };
+
+    let item_name = elem
+        .get_attr("as")
+        .map_or_else(|| "item".to_string(), ToString::to_string);
+    let index_name = elem.get_attr("index").map(ToString::to_string);
+
+    let each = each.clone();
+    let template = elem.children.clone();
+    let env = env.clone();
+
+    dyn_view(move || {
+        let items = match env.attr_value(&each) {
+            Some(Value::List(items)) => items,
+            Some(other) => {
+                log::warn!("For value '{each}' is not a list: {other}");
+                Vec::new()
+            }
+            None => {
+                log::warn!("For value '{each}' could not be resolved");
+                Vec::new()
+            }
+        };
+
+        let rows = items.into_iter().enumerate().map(|(i, item)| {
+            let mut row_env = env.with_local(&item_name, item);
+
+            if let Some(index_name) = &index_name {
+                row_env = row_env.with_local(index_name, Value::from(i));
+            }
+
+            v_stack_from_iter(template.iter().flat_map(|n| views(n, &row_env)))
+        });
+
+        v_stack_from_iter(rows)
+    })
+    .css("for")
+    .into_any()
+}
+
+fn build_custom(name: &str, elem: &Element<'static>, env: &Env) -> AnyView {
+    match component(name, elem, env) {
+        Ok((def, component_env)) => live::instance(name, &def, &component_env, env),
+        Err(view) => {
+            live::track(name, None, env);
+            view
+        }
+    }
+}
+
+/// Definition of the component with the environment it's built in. Errors are returned as
+/// the view shown instead.
+fn component(
+    name: &str,
+    elem: &Element<'static>,
+    env: &Env,
+) -> Result<(Rc<ComponentDef>, Env), AnyView> {
+    let def = match component_def(name) {
+        Some(Ok(def)) => def,
+        Some(Err(errors)) => return Err(parse_errors(&errors)),
+        None => return Err(text(format!("Component not found: {name}")).into_any()),
+    };
+
+    let props = component_props(name, &elem.attributes, def.props(), env);
+    let slots = Slots {
+        nodes: elem.children.clone(),
+        env: env.clone(),
+    };
+    let component_env = Env::component(props, slots, &def.path);
+
+    Ok((def, component_env))
+}
+
+fn component_def(name: &str) -> Option<Result<Rc<ComponentDef>, Vec<ParseError>>> {
+    use_context::<RwSignal<SourceObserver>>()
+        .unwrap()
+        .with_untracked(|o| o.component(name))
+}
+
+/// True for fragments and components with a fragment root. Their children are given to the
+/// parent as separate views.
+fn is_fragment(node: &Node) -> bool {
+    match node {
+        Node::Element(Element {
+            kind: ElementKind::Fragment,
+            ..
+        }) => true,
+        Node::Element(Element {
+            kind: ElementKind::Custom(name),
+            ..
+        }) => matches!(
+            component_def(name),
+            Some(Ok(def)) if matches!(
+                &def.root,
+                Node::Element(Element {
+                    kind: ElementKind::Fragment,
+                    ..
+                })
+            )
+        ),
+        _ => false,
+    }
+}
+
+/// Renders the children given to the component, or the children of the slot as fallback when
+/// there are none
+fn build_slot(elem: &Element<'static>, env: &Env) -> AnyView {
+    let name = elem.get_attr("name").map(ToString::to_string);
+
+    let given = env
+        .slots()
+        .map(|slots| (slots.nodes(name.as_deref()), &slots.env))
+        .filter(|(nodes, _)| !nodes.is_empty());
+
+    let views = match given {
+        Some((nodes, slot_env)) => nodes.into_iter().flat_map(|n| views(n, slot_env)).collect(),
+        None => children(elem, env),
+    };
+
+    v_stack_from_iter(views).css("slot").into_any()
+}
+
+/// Collects props given to component. If the component declares its props, unknown props and
+/// type mismatches are reported and missing props get the declared default.
+fn component_props(
+    name: &str,
+    given: &[Attribute<'static>],
+    declared: Option<&[Attribute<'static>]>,
+    env: &Env,
+) -> Vec<Prop> {
+    let mut props = given
+        .iter()
+        .filter(|a| {
+            !STYLE_ATTRIBUTES.contains(&a.name.as_ref()) && !events::is_event_attribute(&a.name)
+        })
+        .map(|a| Prop::new(&a.name, a.value.clone(), env.clone()))
+        .collect::<Vec<_>>();
+
+    let Some(declared) = declared else {
+        return props;
+    };
+
+    for attr in given {
+        if STYLE_ATTRIBUTES.contains(&attr.name.as_ref()) || events::is_event_attribute(&attr.name)
+        {
+            continue;
+        }
+
+        let Some(default) = declared.iter().find(|d| d.name == attr.name) else {
+            log::warn!("Component '{name}' has no prop '{}'", attr.name);
+            continue;
+        };
+
+        if let (Some(expected), Some(found)) = (value_type(&default.value), value_type(&attr.value))
+        {
+            if expected != found {
+                log::warn!(
+                    "Prop '{}' of component '{name}' expects {expected}, found {found}",
+                    attr.name
+                );
+            }
+        }
+    }
+
+    for default in declared {
+        if !given.iter().any(|a| a.name == default.name) {
+            props.push(Prop::new(
+                &default.name,
+                default.value.clone(),
+                Env::default(),
+            ));
+        }
+    }
+
+    props
+}
+
+/// Type of the value, if it can be known before resolving it
+fn value_type(value: &AttributeValue) -> Option<VariableType> {
+    let kind = match value {
+        AttributeValue::String { .. } => VariableType::String,
+        AttributeValue::Integer { .. } => VariableType::Integer,
+        AttributeValue::Float { .. } => VariableType::Float,
+        AttributeValue::Boolean { .. } => VariableType::Boolean,
+        AttributeValue::Variable { name, .. } => name.kind,
+        AttributeValue::Expression { .. } => VariableType::Unknown,
+    };
+
+    match kind {
+        VariableType::Prop | VariableType::Unknown => None,
+        kind => Some(kind),
+    }
+}
+
+fn children(elem: &Element<'static>, env: &Env) -> Vec<AnyView> {
+    elem.children.iter().flat_map(|n| views(n, env)).collect()
+}
+
+/// Views of the node. Fragments give a view for each child. Style and event attributes of a
+/// component with a fragment root have no view to apply to.
+fn views(n: &Node<'static>, env: &Env) -> Vec<AnyView> {
+    if !is_fragment(n) {
+        return vec![node(n, env)];
+    }
+
+    let Node::Element(elem) = n else {
+        return Vec::new();
+    };
+
+    let env = env.without_path();
+
+    let ElementKind::Custom(name) = &elem.kind else {
+        return elem
+            .children
+            .iter()
+            .flat_map(|child| views(child, &env))
+            .collect();
+    };
+
+    warn_fragment_attributes(name, elem);
+
+    match component(name, elem, &env) {
+        Ok((def, component_env)) => {
+            live::track(name, Some(def.clone()), &env);
+
+            match &def.root {
+                Node::Element(root) => root
+                    .children
+                    .iter()
+                    .flat_map(|child| views(child, &component_env))
+                    .collect(),
+                root => vec![node(root, &component_env)],
+            }
+        }
+        Err(view) => {
+            live::track(name, None, &env);
+            vec![view]
+        }
+    }
+}
+
+fn warn_fragment_attributes(name: &str, elem: &Element) {
+    if elem.attributes.iter().any(|a| {
+        !matches!(a.name.as_ref(), "slot" | "key" | "id")
+            && (STYLE_ATTRIBUTES.contains(&a.name.as_ref()) || events::is_event_attribute(&a.name))
+    }) {
+        log::warn!("Component '{name}' has no root element for style and event attributes");
+    }
+}
+
+/// Children of root, box and stacks. In the document they are updated in place when the
+/// sources change.
+fn container_children(elem: &Element<'static>, env: &Env) -> AnyView {
+    live::children(elem, env).unwrap_or_else(|| stack_from_iter(children(elem, env)).into_any())
+}
+
+fn parse_errors(errors: &[ParseError]) -> AnyView {
+    for e in errors {
+        log::error!("{e}");
+    }
+
+    let content = errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    text(content).into_any()
+}
+
+fn attr_value_to_px_pct(value: &AttributeValue, env: &Env) -> PxPct {
+    match value {
+        AttributeValue::String { value, .. } => parse_px_pct(value).unwrap_or(PxPct::Px(0.0)),
+        AttributeValue::Float { value, .. } => PxPct::Px(*value),
+        AttributeValue::Integer { value, .. } => PxPct::Px(*value as f64),
+        AttributeValue::Boolean { .. } => PxPct::Px(0.0),
+        AttributeValue::Variable { .. } | AttributeValue::Expression { .. } => {
+            match env.attr_value(value) {
+                Some(Value::String(v)) => parse_px_pct(&v).unwrap_or(PxPct::Px(0.0)),
+                Some(Value::Float(v)) => PxPct::Px(v),
+                Some(Value::Integer(v)) => PxPct::Px(v as f64),
+                other => {
+                    warn_not_size(value, other);
+                    PxPct::Px(0.0)
+                }
+            }
+        }
+    }
+}
+
+fn attr_value_to_px_pct_auto(value: &AttributeValue, env: &Env) -> PxPctAuto {
+    match value {
+        AttributeValue::String { value, .. } => {
+            if value == "auto" {
+                PxPctAuto::Auto
+            } else {
+                parse_pxpctauto(value).unwrap_or(PxPctAuto::Auto)
+            }
+        }
+        AttributeValue::Float { value, .. } => PxPctAuto::Px(*value),
+        AttributeValue::Integer { value, .. } => PxPctAuto::Px(*value as f64),
+        AttributeValue::Boolean { .. } => PxPctAuto::Auto,
+        AttributeValue::Variable { .. } | AttributeValue::Expression { .. } => {
+            match env.attr_value(value) {
+                Some(Value::String(v)) if v == "auto" => PxPctAuto::Auto,
+                Some(Value::String(v)) => parse_pxpctauto(&v).unwrap_or(PxPctAuto::Auto),
+                Some(Value::Float(v)) => PxPctAuto::Px(v),
+                Some(Value::Integer(v)) => PxPctAuto::Px(v as f64),
+                other => {
+                    warn_not_size(value, other);
+                    PxPctAuto::Auto
+                }
+            }
+        }
+    }
+}
+
+fn warn_not_size(value: &AttributeValue, found: Option<Value>) {
+    match found {
+        Some(found) => log::warn!("Expected size from '{value}', found '{found}'"),
+        None => log::warn!("Size '{value}' has no value"),
+    }
+}
+
+fn attr_value_to_color(value: &AttributeValue, env: &Env) -> Color {
+    match env.attr_value(value) {
+        Some(Value::String(value)) => parse_color(&value).unwrap_or(Color::WHITE),
+        _ => Color::WHITE,
+    }
+}
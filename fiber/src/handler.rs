@@ -0,0 +1,123 @@
+use std::rc::Rc;
+
+use floem::event::Event;
+use floem::keyboard::Key;
+use fml::Value;
+
+use crate::state::HandlerFn;
+
+/// Data of the event that triggered a handler
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum EventPayload {
+    #[default]
+    None,
+    Pointer {
+        x: f64,
+        y: f64,
+    },
+    Key {
+        key: String,
+        ctrl: bool,
+        shift: bool,
+        alt: bool,
+        meta: bool,
+    },
+    Scroll {
+        delta_x: f64,
+        delta_y: f64,
+    },
+    Text(String),
+}
+
+impl From<&Event> for EventPayload {
+    fn from(event: &Event) -> Self {
+        match event {
+            Event::PointerDown(e) | Event::PointerUp(e) => EventPayload::Pointer {
+                x: e.pos.x,
+                y: e.pos.y,
+            },
+            Event::PointerMove(e) => EventPayload::Pointer {
+                x: e.pos.x,
+                y: e.pos.y,
+            },
+            Event::PointerWheel(e) => EventPayload::Scroll {
+                delta_x: e.delta.x,
+                delta_y: e.delta.y,
+            },
+            Event::KeyDown(e) | Event::KeyUp(e) => EventPayload::Key {
+                key: match &e.key.logical_key {
+                    Key::Character(c) => c.to_string(),
+                    Key::Named(named) => format!("{named:?}"),
+                    other => format!("{other:?}"),
+                },
+                ctrl: e.modifiers.control_key(),
+                shift: e.modifiers.shift_key(),
+                alt: e.modifiers.alt_key(),
+                meta: e.modifiers.super_key(),
+            },
+            _ => EventPayload::None,
+        }
+    }
+}
+
+/// Arguments given to a handler in FML, like `{remove_item(int:row_id)}`, and the event that
+/// triggered it
+#[derive(Debug, Clone, Default)]
+pub struct HandlerArgs {
+    pub args: Vec<Value>,
+    pub payload: EventPayload,
+}
+
+impl HandlerArgs {
+    /// Converts argument at `index` for a `#[arg]` parameter. Used by `fiber::task`.
+    pub fn arg<T: TryFrom<Value>>(&self, index: usize, name: &str) -> Option<T> {
+        let Some(value) = self.args.get(index) else {
+            log::error!("Missing argument '{name}'");
+            return None;
+        };
+
+        T::try_from(value.clone())
+            .map_err(|_| {
+                log::error!(
+                    "Invalid argument '{name}': expected {}, got {value:?}",
+                    std::any::type_name::<T>()
+                );
+            })
+            .ok()
+    }
+}
+
+/// Handler with the name used in FML. Created from `#[fiber::task]` functions, `(name, fn)`
+/// pairs or with `NamedHandler::new` for closures.
+pub struct NamedHandler {
+    pub(crate) name: String,
+    pub(crate) f: HandlerFn,
+}
+
+impl NamedHandler {
+    pub fn new(name: impl Into<String>, f: impl Fn(&HandlerArgs) + 'static) -> Self {
+        NamedHandler {
+            name: name.into(),
+            f: Rc::new(f),
+        }
+    }
+
+    /// Handler stored as is, so the same closure can be shared
+    pub fn from_rc(name: impl Into<String>, f: HandlerFn) -> Self {
+        NamedHandler {
+            name: name.into(),
+            f,
+        }
+    }
+}
+
+impl<S, F> From<(S, F)> for NamedHandler
+where
+    S: Into<String>,
+    F: Fn(&HandlerArgs) + 'static,
+{
+    fn from((name, f): (S, F)) -> Self {
+        let name: String = name.into();
+        NamedHandler::new(name.replace("_fibr_", ""), f)
+    }
+}
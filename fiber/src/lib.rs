@@ -5,8 +5,12 @@
 )]
 
 mod app;
+mod assets;
 mod builders;
+pub mod handler;
 mod observer;
+#[cfg(debug_assertions)]
+mod overlay;
 pub mod state;
 pub mod task;
 mod theme;
@@ -16,5 +20,7 @@ pub use fiber_macro::task;
 
 // Export common structs
 pub use app::App;
+pub use fml::Value;
+pub use handler::{EventPayload, HandlerArgs, NamedHandler};
 pub use state::StateCtx;
 pub use theme::StyleCss;
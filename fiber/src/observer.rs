@@ -1,10 +1,21 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use crossbeam_channel::Sender;
+use fml::{
+    Attribute, Diagnostic, DiagnosticKind, Element, ElementKind, Node, ParseError, ParseErrorKind,
+    Span, Validator,
+};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 
+use crate::state::State;
+use crate::theme::Theme;
+
 #[derive(Clone)]
 pub struct SourceObserver {
     _observer: Rc<FileObserver>,
@@ -18,7 +29,8 @@ impl SourceObserver {
     pub fn new(path: &Path, sender: Sender<()>) -> Result<Self, Box<dyn std::error::Error>> {
         let observer = FileObserver::new(path, sender, true)?;
         log::info!("Runtime observing {path:?}");
-        let source_map = SourceMap::try_from(path)?;
+        // Sources that fail to load are shown as errors until a change fixes them
+        let source_map = SourceMap::try_from(path).unwrap_or_else(SourceMap::failed);
 
         Ok(SourceObserver {
             _observer: Rc::new(observer),
@@ -27,96 +39,616 @@ impl SourceObserver {
         })
     }
 
-    pub fn update(&mut self) {
-        if let Ok(new_map) = SourceMap::try_from(self.path.as_path()) {
-            self.source_map = new_map;
-        } else {
-            log::error!("Source map update failed!");
+    /// Reloads the sources. Previous sources are kept if any file fails to load or parse, so
+    /// the last working UI can stay on screen.
+    ///
+    /// # Errors
+    /// Returns the errors of the files that failed
+    pub fn update(&mut self) -> Result<(), Vec<FileError>> {
+        let source_map = SourceMap::load(&self.path, self.source_map.cache.clone())
+            .map_err(|e| vec![FileError::from(e)])?;
+
+        if !source_map.errors.is_empty() {
+            return Err(source_map.errors);
         }
+
+        self.source_map = source_map;
+
+        Ok(())
+    }
+
+    /// Parse errors of the sources in use
+    pub fn errors(&self) -> &[FileError] {
+        &self.source_map.errors
     }
 
     pub fn main(&self) -> &str {
         &self.source_map.main
     }
 
-    pub fn component(&self, name: &str) -> Option<&String> {
-        self.source_map.components.get(name)
+    pub fn main_path(&self) -> PathBuf {
+        self.path.join("main.fml")
+    }
+
+    /// Parsed component. Component files are parsed once for each content, instances share
+    /// the result.
+    pub fn component(&self, name: &str) -> Option<Result<Rc<ComponentDef>, Vec<ParseError>>> {
+        let component = self.source_map.components.get(name)?;
+
+        Some(match component {
+            Component::Element(def) => Ok(def.clone()),
+            Component::Source(path, source) => self.source_map.cache.get(path, source),
+        })
+    }
+
+    /// Checks the main file and components against the handlers and variables in state and
+    /// the classes in theme. Files with parse errors are skipped, those are shown when built.
+    pub(crate) fn validate(&self, state: &State, theme: &Theme) -> Vec<Diagnostic> {
+        let mut validator = Validator::new()
+            .handlers(state.handler_names())
+            .vars(state.variable_types())
+            .components(self.source_map.components.keys().cloned())
+            .classes(theme.classes().cloned());
+
+        if let Ok(main) = fml::parse(&self.source_map.main) {
+            validator.validate("main.fml", &main);
+        }
+
+        for (name, component) in &self.source_map.components {
+            let path = component.path();
+            let file = path
+                .strip_prefix(&self.path)
+                .unwrap_or(path)
+                .display()
+                .to_string();
+
+            match component {
+                Component::Source(path, source) => {
+                    if let Ok(def) = self.source_map.cache.get(path, source) {
+                        validator.validate_component(name, &file, &def.declared_root());
+                    }
+                }
+                Component::Element(def) => {
+                    validator.validate_component(name, &file, &def.declared_root());
+                }
+            }
+        }
+
+        // Debug handlers are always registered
+        validator
+            .finish()
+            .into_iter()
+            .filter(|d| !matches!(&d.kind, DiagnosticKind::UnusedHandler(name) if name.starts_with("dbg_")))
+            .collect()
+    }
+}
+
+/// Source of a component and the file it's in. Files in `components` and imported
+/// directories are parsed when used, components defined in imported module files are kept
+/// parsed.
+#[derive(Clone, PartialEq)]
+pub enum Component {
+    Source(PathBuf, String),
+    Element(Rc<ComponentDef>),
+}
+
+impl Component {
+    pub fn path(&self) -> &Path {
+        match self {
+            Component::Source(path, _) => path,
+            Component::Element(def) => &def.path,
+        }
+    }
+}
+
+/// Parsed component with the `<props>` declaration taken out of the root
+#[derive(PartialEq)]
+pub struct ComponentDef {
+    pub path: PathBuf,
+    pub root: Node<'static>,
+    pub props: Option<Element<'static>>,
+}
+
+impl ComponentDef {
+    fn new(path: &Path, mut root: Element<'static>) -> ComponentDef {
+        let props = root
+            .children
+            .iter()
+            .position(|n| matches!(n, Node::Element(e) if matches!(e.kind, ElementKind::Props)))
+            .map(|i| root.children.remove(i))
+            .and_then(|n| match n {
+                Node::Element(props) => Some(props),
+                _ => None,
+            });
+
+        ComponentDef {
+            path: path.to_path_buf(),
+            root: Node::Element(root),
+            props,
+        }
+    }
+
+    /// Declared props with their default values
+    pub fn props(&self) -> Option<&[Attribute<'static>]> {
+        self.props.as_ref().map(|p| p.attributes.as_slice())
+    }
+
+    /// Root with the props declaration, like it's written
+    fn declared_root(&self) -> Node<'static> {
+        match (&self.root, &self.props) {
+            (Node::Element(root), Some(props)) => {
+                let mut root = root.clone();
+                root.children.insert(0, Node::Element(props.clone()));
+                Node::Element(root)
+            }
+            (root, _) => root.clone(),
+        }
+    }
+}
+
+/// Parsed component files by path and content hash. Kept over reloads so that only changed
+/// files are parsed again.
+#[derive(Clone, Default)]
+pub(crate) struct ParseCache(Rc<RefCell<HashMap<PathBuf, (u64, Rc<ComponentDef>)>>>);
+
+impl ParseCache {
+    /// Files are cached by canonical path, so a file reached by different paths is parsed once
+    fn key(path: &Path) -> PathBuf {
+        path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    fn get(&self, path: &Path, source: &str) -> Result<Rc<ComponentDef>, Vec<ParseError>> {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let key = ParseCache::key(path);
+
+        if let Some((cached, def)) = self.0.borrow().get(&key) {
+            if *cached == hash {
+                return Ok(def.clone());
+            }
+        }
+
+        let root = match fml::parse(source)? {
+            Node::Element(root) => root.into_owned(),
+            _ => {
+                let span = Span {
+                    line: 1,
+                    col: 1,
+                    ..Span::default()
+                };
+                return Err(vec![ParseError::new(ParseErrorKind::NoRootElement, span)]);
+            }
+        };
+
+        let def = Rc::new(ComponentDef::new(path, root));
+        self.0.borrow_mut().insert(key, (hash, def.clone()));
+
+        Ok(def)
+    }
+}
+
+/// Error in a source file, with the line it points to
+#[derive(Debug, Clone)]
+pub struct FileError {
+    pub file: PathBuf,
+    pub span: Option<Span>,
+    pub message: String,
+    pub snippet: Option<String>,
+}
+
+impl FileError {
+    fn parse(file: &Path, source: &str, error: &ParseError) -> FileError {
+        let span = error.span();
+
+        FileError {
+            file: file.to_path_buf(),
+            span: Some(span),
+            message: error.kind().to_string(),
+            snippet: source
+                .lines()
+                .nth(span.line.saturating_sub(1))
+                .map(ToString::to_string),
+        }
+    }
+}
+
+impl From<SourceError> for FileError {
+    fn from(error: SourceError) -> Self {
+        let file = match &error {
+            SourceError::Io(path, _) => path.clone(),
+            SourceError::ImportCycle(paths) => paths.last().cloned().unwrap_or_default(),
+        };
+
+        FileError {
+            file,
+            span: None,
+            message: error.to_string(),
+            snippet: None,
+        }
+    }
+}
+
+impl Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.span {
+            Some(span) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.file.display(),
+                span.line,
+                span.col,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SourceError {
+    Io(PathBuf, std::io::Error),
+    ImportCycle(Vec<PathBuf>),
+}
+
+impl Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceError::Io(path, e) => write!(f, "Failed to read {path:?}: {e}"),
+            SourceError::ImportCycle(paths) => {
+                let chain = paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                write!(f, "Import cycle: {chain}")
+            }
+        }
     }
 }
 
+impl std::error::Error for SourceError {}
+
 #[derive(Clone)]
 pub struct SourceMap {
     pub main: String,
-    pub components: HashMap<String, String>,
+    pub components: HashMap<String, Component>,
+    /// Parse errors of the loaded files
+    pub errors: Vec<FileError>,
+    cache: ParseCache,
 }
 
 impl TryFrom<&Path> for SourceMap {
-    type Error = std::io::Error;
+    type Error = SourceError;
 
     fn try_from(path: &Path) -> Result<Self, Self::Error> {
-        let main = std::fs::read_to_string(path.join("main.fml"))?;
+        SourceMap::load(path, ParseCache::default())
+    }
+}
 
-        let mut components = HashMap::new();
+impl SourceMap {
+    fn load(path: &Path, cache: ParseCache) -> Result<Self, SourceError> {
+        let main_path = path.join("main.fml");
+        let main = std::fs::read_to_string(&main_path)
+            .map_err(|e| SourceError::Io(main_path.clone(), e))?;
 
-        let mut read_dir = |path: &Path| {
-            let Ok(dir_entry) = std::fs::read_dir(path) else {
-                log::warn!("Failed to read dir: {:?}", path);
-                return;
-            };
+        let mut map = SourceMap {
+            main,
+            components: HashMap::new(),
+            errors: Vec::new(),
+            cache,
+        };
 
-            for entry in dir_entry {
-                let Ok(entry) = entry else {
-                    log::warn!("Invalid entry: {entry:?}");
-                    continue;
-                };
+        let files = if path.join("components").exists() {
+            fml_files(&path.join("components"))
+        } else {
+            Vec::new()
+        };
 
-                let Ok(meta) = entry.metadata() else {
-                    log::warn!("Failed to read entry metadata: {entry:?}");
-                    continue;
-                };
+        for (name, file) in &files {
+            if let Ok(source) = read_source(file) {
+                map.add(name.clone(), Component::Source(file.clone(), source));
+            }
+        }
 
-                let path = entry.path();
+        let mut loader = ImportLoader {
+            map: &mut map,
+            stack: Vec::new(),
+            loaded: HashSet::new(),
+        };
 
-                if !meta.is_file() {
-                    continue;
+        // Import errors are kept with the parse errors, so they are shown the same way
+        let loaded = loader
+            .load(&main_path)
+            .and_then(|()| files.iter().try_for_each(|(_, file)| loader.load(file)));
+
+        let loaded_files = loader.loaded;
+
+        if let Err(e) = loaded {
+            map.errors.push(FileError::from(e));
+        }
+
+        // Files that are no longer used are dropped from the cache
+        let component_files = map
+            .components
+            .values()
+            .map(|c| ParseCache::key(c.path()))
+            .collect::<HashSet<_>>();
+        map.cache
+            .0
+            .borrow_mut()
+            .retain(|path, _| loaded_files.contains(path) || component_files.contains(path));
+
+        Ok(map)
+    }
+
+    /// Empty sources with the error that stopped loading
+    fn failed(error: SourceError) -> SourceMap {
+        SourceMap {
+            main: String::new(),
+            components: HashMap::new(),
+            errors: vec![FileError::from(error)],
+            cache: ParseCache::default(),
+        }
+    }
+
+    fn add(&mut self, name: String, component: Component) {
+        log::info!("Added component: {name}");
+
+        if self.components.insert(name.clone(), component).is_some() {
+            log::warn!("Duplicate component: {name}");
+        }
+    }
+
+    fn add_errors(&mut self, file: &Path, source: &str, errors: &[ParseError]) {
+        // Modules imported more than once are parsed again for each import
+        if self.errors.iter().any(|e| e.file == file) {
+            return;
+        }
+
+        self.errors
+            .extend(errors.iter().map(|e| FileError::parse(file, source, e)));
+    }
+}
+
+/// Follows `<import src=".." as=".."/>` elements. Paths are relative to the importing file.
+/// Directories import every file in them as a component, files import the `<component>`
+/// elements defined in them. Components are named `alias:name`.
+struct ImportLoader<'a> {
+    map: &'a mut SourceMap,
+    stack: Vec<PathBuf>,
+    loaded: HashSet<PathBuf>,
+}
+
+impl ImportLoader<'_> {
+    /// Loads imports of the file. Files already loaded are skipped, files still being loaded
+    /// are an import cycle.
+    fn load(&mut self, file: &Path) -> Result<(), SourceError> {
+        let file = file
+            .canonicalize()
+            .map_err(|e| SourceError::Io(file.to_path_buf(), e))?;
+
+        if let Some(i) = self.stack.iter().position(|p| p == &file) {
+            let mut cycle = self.stack[i..].to_vec();
+            cycle.push(file);
+            return Err(SourceError::ImportCycle(cycle));
+        }
+
+        if !self.loaded.insert(file.clone()) {
+            return Ok(());
+        }
+
+        let source = read_source(&file)?;
+
+        // Unchanged files are taken from the cache instead of parsed again
+        let def = match self.map.cache.get(&file, &source) {
+            Ok(def) => def,
+            Err(errors) => {
+                self.map.add_errors(&file, &source, &errors);
+                return Ok(());
+            }
+        };
+
+        let mut imports = Vec::new();
+        if let Node::Element(root) = &def.root {
+            collect_imports(root, &mut imports);
+        }
+
+        self.stack.push(file.clone());
+
+        let dir = file.parent().unwrap_or(Path::new("."));
+
+        for (src, alias) in imports {
+            let path = dir.join(&src);
+
+            if !path.exists() {
+                log::error!("Import not found: {path:?} in {file:?}");
+                continue;
+            }
+
+            let alias = alias.unwrap_or_else(|| {
+                path.file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            });
+
+            if path.is_dir() {
+                for (name, component_file) in fml_files(&path) {
+                    let Ok(source) = read_source(&component_file) else {
+                        continue;
+                    };
+                    let component = Component::Source(component_file.clone(), source);
+                    self.map.add(format!("{alias}:{name}"), component);
+                    self.load(&component_file)?;
                 }
+            } else {
+                self.load_module(&path, &alias)?;
+            }
+        }
 
-                if !path
-                    .extension()
-                    .is_some_and(|ext| ext.eq_ignore_ascii_case("fml"))
-                {
+        self.stack.pop();
+
+        Ok(())
+    }
+
+    /// Module file has `<component name="..">` elements, each with a root element or several
+    /// elements that make a fragment
+    fn load_module(&mut self, path: &Path, alias: &str) -> Result<(), SourceError> {
+        // Same path as in `load`, so the module is parsed once
+        let path = path
+            .canonicalize()
+            .map_err(|e| SourceError::Io(path.to_path_buf(), e))?;
+        let source = read_source(&path)?;
+
+        let def = match self.map.cache.get(&path, &source) {
+            Ok(def) => def,
+            Err(errors) => {
+                self.map.add_errors(&path, &source, &errors);
+                return Ok(());
+            }
+        };
+
+        // Props of a single component are taken out of the root by the cache
+        let Node::Element(root) = def.declared_root() else {
+            return Ok(());
+        };
+
+        // Single component doesn't need a `<module>` around it
+        let components = match root.kind {
+            ElementKind::Component => vec![Node::Element(root)],
+            _ => root.children,
+        };
+
+        for node in components {
+            let Node::Element(component) = node else {
+                continue;
+            };
+
+            if !matches!(component.kind, ElementKind::Component) {
+                continue;
+            }
+
+            let Some(name) = component.get_attr("name").map(ToString::to_string) else {
+                log::warn!("Component without name in {path:?}");
+                continue;
+            };
+
+            let (mut props, mut elements): (Vec<_>, Vec<_>) = component
+                .children
+                .into_iter()
+                .filter(|n| matches!(n, Node::Element(_)))
+                .partition(
+                    |n| matches!(n, Node::Element(e) if matches!(e.kind, ElementKind::Props)),
+                );
+
+            // Several elements are a fragment like in component files
+            let mut root = match elements.len() {
+                0 => {
+                    log::warn!("Component '{name}' in {path:?} has no elements");
                     continue;
                 }
+                1 => match elements.pop() {
+                    Some(Node::Element(root)) => root,
+                    _ => continue,
+                },
+                _ => Element::new("", Vec::new(), elements, component.span),
+            };
 
-                let Some(name) = path.file_stem() else {
-                    log::warn!("Failed to get file stem from: {:?}", path);
-                    continue;
-                };
+            // Props are read from the root like in component files
+            props.append(&mut root.children);
+            root.children = props;
 
-                let Some(name) = name.to_str() else {
-                    log::warn!("Failed to create str from OsStr: {:?}", name);
-                    continue;
-                };
+            let component = Component::Element(Rc::new(ComponentDef::new(&path, root)));
+            self.map.add(format!("{alias}:{name}"), component);
+        }
 
-                let Ok(source) = std::fs::read_to_string(entry.path()) else {
-                    log::warn!("Failed to read file content: {:?}", path);
-                    continue;
-                };
+        self.load(&path)
+    }
+}
 
-                if components.insert(name.to_string(), source).is_some() {
-                    log::warn!("Duplicate component: {name}");
-                }
+/// Import elements anywhere in the tree as `(src, as)` pairs
+fn collect_imports(elem: &Element, imports: &mut Vec<(String, Option<String>)>) {
+    for child in &elem.children {
+        let Node::Element(child) = child else {
+            continue;
+        };
 
-                log::info!("Added component: {name}");
+        if matches!(child.kind, ElementKind::Import) {
+            match child.get_attr("src") {
+                Some(src) => imports.push((
+                    src.to_string(),
+                    child.get_attr("as").map(ToString::to_string),
+                )),
+                None => log::warn!("Import without src"),
             }
+        } else {
+            collect_imports(child, imports);
+        }
+    }
+}
+
+fn read_source(path: &Path) -> Result<String, SourceError> {
+    std::fs::read_to_string(path).map_err(|e| SourceError::Io(path.to_path_buf(), e))
+}
+
+/// All `.fml` files in the directory and its subdirectories, named by file stem. Files in
+/// subdirectories are named with the directories, like `forms:textfield`.
+fn fml_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut files = Vec::new();
+
+    let Ok(dir_entry) = std::fs::read_dir(dir) else {
+        log::warn!("Failed to read dir: {:?}", dir);
+        return files;
+    };
+
+    for entry in dir_entry {
+        let Ok(entry) = entry else {
+            log::warn!("Invalid entry: {entry:?}");
+            continue;
         };
 
-        if path.join("components").exists() {
-            read_dir(&path.join("components"));
+        let Ok(meta) = entry.metadata() else {
+            log::warn!("Failed to read entry metadata: {entry:?}");
+            continue;
+        };
+
+        let path = entry.path();
+
+        if meta.is_dir() {
+            let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) else {
+                log::warn!("Failed to create str from OsStr: {:?}", path);
+                continue;
+            };
+
+            for (name, file) in fml_files(&path) {
+                files.push((format!("{dir_name}:{name}"), file));
+            }
+
+            continue;
+        }
+
+        if !path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("fml"))
+        {
+            continue;
         }
 
-        Ok(Self { main, components })
+        let Some(name) = path.file_stem() else {
+            log::warn!("Failed to get file stem from: {:?}", path);
+            continue;
+        };
+
+        let Some(name) = name.to_str() else {
+            log::warn!("Failed to create str from OsStr: {:?}", name);
+            continue;
+        };
+
+        files.push((name.to_string(), path.clone()));
     }
+
+    files
 }
 
 pub(crate) struct FileObserver {
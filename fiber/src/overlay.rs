@@ -0,0 +1,73 @@
+use floem::peniko::Color;
+use floem::reactive::{create_effect, RwSignal};
+use floem::style::Style;
+use floem::views::{button, dyn_view, h_stack, label, v_stack, v_stack_from_iter, Decorators};
+use floem::{IntoView, View};
+
+use crate::observer::FileError;
+
+/// Lists source errors over the UI. Hidden when there are no errors, and until the errors
+/// change after it's dismissed.
+pub(crate) fn error_overlay(errors: RwSignal<Vec<FileError>>) -> impl View {
+    let dismissed = RwSignal::new(false);
+    // Sources have built if there were no errors at startup or after a reload
+    let built = RwSignal::new(false);
+
+    create_effect(move |_| {
+        if errors.with(Vec::is_empty) {
+            built.set(true);
+        }
+        dismissed.set(false);
+    });
+
+    let header = h_stack((
+        label(move || {
+            let count = errors.with(Vec::len);
+
+            if built.get() {
+                format!("{count} error(s) in sources, showing the last working version")
+            } else {
+                format!("{count} error(s) in sources, nothing has built yet")
+            }
+        }),
+        button(|| "Dismiss").on_click_stop(move |_| dismissed.set(true)),
+    ))
+    .style(|s| s.width_full().items_center().justify_between());
+
+    let list = dyn_view(move || {
+        errors.with(|errors| {
+            v_stack_from_iter(errors.iter().map(error_view))
+                .style(|s| s.gap(12.0))
+                .into_any()
+        })
+    });
+
+    v_stack((header, list))
+        .debug_name("Error overlay")
+        .style(move |s| {
+            s.absolute()
+                .size_full()
+                .padding(16.0)
+                .gap(16.0)
+                .background(Color::rgba8(24, 24, 24, 235))
+                .color(Color::rgb8(240, 240, 240))
+                .apply_if(dismissed.get() || errors.with(Vec::is_empty), Style::hide)
+        })
+}
+
+fn error_view(error: &FileError) -> impl View {
+    let title = error.to_string();
+    let snippet = match (&error.snippet, error.span) {
+        (Some(line), Some(span)) => {
+            format!("{line}\n{}^", " ".repeat(span.col.saturating_sub(1)))
+        }
+        (Some(line), None) => line.clone(),
+        (None, _) => String::new(),
+    };
+
+    v_stack((
+        label(move || title.clone()).style(|s| s.color(Color::rgb8(255, 120, 120))),
+        label(move || snippet.clone()).style(|s| s.font_family("monospace".to_string())),
+    ))
+    .style(|s| s.gap(4.0))
+}
@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::hash::Hash;
 use std::ops::Deref;
@@ -7,8 +8,10 @@ use std::rc::Rc;
 
 use dashmap::DashMap;
 use floem::reactive::RwSignal;
-use floem::{AnyView, View};
-use fml::VariableType;
+use floem::{AnyView, View, ViewId};
+use fml::{Value, VariableType};
+
+use crate::handler::{HandlerArgs, NamedHandler};
 
 pub trait Viewable: View + Any {
     fn into_anyview(&self) -> AnyView;
@@ -43,9 +46,11 @@ where
 
 #[derive(Default)]
 pub struct State {
-    pub(crate) fns: DashMap<String, FnPointer>,
+    pub(crate) fns: DashMap<String, HandlerFn>,
     pub(crate) variables: DashMap<VariableKey, RwSignal<Box<dyn Any>>>,
     pub(crate) viewables: DashMap<String, RwSignal<Vec<Box<dyn Viewable>>>>,
+    /// Built views by the `id` attribute of their element
+    pub(crate) view_ids: DashMap<String, ViewId>,
 }
 
 pub struct VariableKey {
@@ -125,7 +130,11 @@ fn dbg_print_state(state: StateCtx) {
     }
 }
 
-pub type FnPointer = fn();
+/// Handler generated by `#[fiber::task]`
+pub type FnPointer = fn(&HandlerArgs);
+
+/// Handler stored in state. Tasks and closures are both stored as this.
+pub type HandlerFn = Rc<dyn Fn(&HandlerArgs)>;
 
 impl State {
     #[must_use]
@@ -144,48 +153,84 @@ impl State {
             return;
         };
 
-        for line in content.lines() {
-            let parts = line.split([':', ' ']).collect::<Vec<_>>();
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            // Name ends at the second colon or the first space, the rest is the value
+            let Some((t, name, d)) = line
+                .split_once(':')
+                .and_then(|(t, rest)| rest.split_once([':', ' ']).map(|(n, d)| (t, n, d.trim())))
+            else {
+                log::warn!("Invalid variable definition: {line}");
+                continue;
+            };
 
-            if let [t, name, d] = parts[..] {
-                let kind = VariableType::from(t);
+            let kind = VariableType::from(t);
 
-                match kind {
-                    VariableType::String | VariableType::Unknown => {
-                        log::info!("Created String variable: {name}");
-                        let boxed_val: Box<dyn Any> = Box::new(d.to_string());
-                        self.variables
-                            .insert(VariableKey::new::<String>(name), RwSignal::new(boxed_val));
-                    }
-                    VariableType::Integer => {
-                        log::info!("Created i64 variable: {name}");
-                        let boxed_val: Box<dyn Any> =
-                            Box::new(d.parse::<i64>().unwrap_or_default());
-                        self.variables
-                            .insert(VariableKey::new::<i64>(name), RwSignal::new(boxed_val));
+            match kind {
+                VariableType::String | VariableType::Unknown => {
+                    log::info!("Created String variable: {name}");
+                    let boxed_val: Box<dyn Any> = Box::new(d.to_string());
+                    self.variables
+                        .insert(VariableKey::new::<String>(name), RwSignal::new(boxed_val));
+                }
+                VariableType::Integer => {
+                    log::info!("Created i64 variable: {name}");
+                    let boxed_val: Box<dyn Any> = Box::new(d.parse::<i64>().unwrap_or_default());
+                    self.variables
+                        .insert(VariableKey::new::<i64>(name), RwSignal::new(boxed_val));
+                }
+                VariableType::Float => {
+                    log::info!("Created f64 variable: {name}");
+                    let boxed_val: Box<dyn Any> = Box::new(d.parse::<f64>().unwrap_or_default());
+                    self.variables
+                        .insert(VariableKey::new::<f64>(name), RwSignal::new(boxed_val));
+                }
+                VariableType::Prop => {
+                    log::warn!("Props can't be declared in vars file: {line}");
+                }
+                VariableType::Boolean => {
+                    log::info!("Created bool variable: {name}");
+                    let boxed_val: Box<dyn Any> = Box::new(d.parse::<bool>().unwrap_or_default());
+                    self.variables
+                        .insert(VariableKey::new::<bool>(name), RwSignal::new(boxed_val));
+                }
+                VariableType::List => match d.parse::<Value>() {
+                    Ok(Value::List(list)) => {
+                        log::info!("Created list variable: {name}");
+                        let boxed_val: Box<dyn Any> = Box::new(list);
+                        self.variables.insert(
+                            VariableKey::new::<Vec<Value>>(name),
+                            RwSignal::new(boxed_val),
+                        );
                     }
-                    VariableType::Float => {
-                        log::info!("Created f64 variable: {name}");
-                        let boxed_val: Box<dyn Any> =
-                            Box::new(d.parse::<f64>().unwrap_or_default());
-                        self.variables
-                            .insert(VariableKey::new::<f64>(name), RwSignal::new(boxed_val));
+                    Ok(other) => log::warn!("Value of list variable '{name}' is {}", other.kind()),
+                    Err(e) => log::warn!("Invalid list variable '{name}': {e}"),
+                },
+                VariableType::Map => match d.parse::<Value>() {
+                    Ok(Value::Map(map)) => {
+                        log::info!("Created map variable: {name}");
+                        let boxed_val: Box<dyn Any> = Box::new(map);
+                        self.variables.insert(
+                            VariableKey::new::<BTreeMap<String, Value>>(name),
+                            RwSignal::new(boxed_val),
+                        );
                     }
-                };
-            } else {
-                log::warn!("Invalid variable definition: {line}");
-            }
+                    Ok(other) => log::warn!("Value of map variable '{name}' is {}", other.kind()),
+                    Err(e) => log::warn!("Invalid map variable '{name}': {e}"),
+                },
+            };
         }
     }
 
-    pub fn set_fn(&self, key: String, f: FnPointer) {
-        self.fns.insert(key, f);
+    /// Sets handler, replacing existing one with the same name. Accepts closures, so the
+    /// handler can capture data.
+    pub fn set_fn(&self, key: impl Into<String>, f: impl Fn(&HandlerArgs) + 'static) {
+        self.fns.insert(key.into(), Rc::new(f));
     }
 
     /// # Panics
     /// Panics if the handler already exists
-    pub fn add_handler(&self, (key, f): (String, FnPointer)) {
-        let name = key.replace("_fibr_", "");
+    pub fn add_handler(&self, handler: impl Into<NamedHandler>) {
+        let NamedHandler { name, f } = handler.into();
         assert!(
             self.fns.insert(name.clone(), f).is_none(),
             "Handler already exists: {name}"
@@ -197,6 +242,12 @@ impl State {
         self.variables.view(&VariableKey::new::<T>(key), |_, v| *v)
     }
 
+    /// Reads variable as `Value`. Subscribes to the variable when called in reactive context.
+    #[must_use]
+    pub fn get_value(&self, key: &str) -> Option<Value> {
+        self.get::<Value>(key)?.with(|v| value_from_any(&**v))
+    }
+
     pub fn get_view(&self, key: &str) -> Option<RwSignal<Vec<Box<dyn Viewable>>>> {
         self.viewables.view(key, |_, v| *v)
     }
@@ -289,7 +340,67 @@ impl State {
     }
 
     #[must_use]
-    pub fn get_fn(&self, key: &str) -> Option<FnPointer> {
-        self.fns.get(key).map(|w| *w)
+    pub fn get_fn(&self, key: &str) -> Option<HandlerFn> {
+        self.fns.get(key).map(|f| f.clone())
+    }
+
+    /// View built from the element with `id="..."`, to focus, scroll to or inspect it.
+    /// Elements that are built more than once, like in `for` or in a component used in several
+    /// places, give the view built last.
+    #[must_use]
+    pub fn view_id(&self, id: &str) -> Option<ViewId> {
+        self.view_ids.get(id).map(|v| *v)
+    }
+
+    pub(crate) fn set_view_id(&self, id: &str, view: ViewId) {
+        self.view_ids.insert(id.to_string(), view);
+    }
+
+    /// Forgets the views when the whole document is built again
+    pub(crate) fn clear_view_ids(&self) {
+        self.view_ids.clear();
+    }
+
+    pub(crate) fn handler_names(&self) -> Vec<String> {
+        self.fns.iter().map(|e| e.key().clone()).collect()
+    }
+
+    /// Names and types of variables and viewables. Values that don't convert to `Value` have
+    /// unknown type.
+    pub(crate) fn variable_types(&self) -> Vec<(String, VariableType)> {
+        let variables = self.variables.iter().map(|e| {
+            let kind = e
+                .value()
+                .with_untracked(|v| value_from_any(&**v))
+                .map_or(VariableType::Unknown, |v| v.kind());
+            (e.key().name.clone(), kind)
+        });
+
+        let viewables = self
+            .viewables
+            .iter()
+            .map(|e| (e.key().clone(), VariableType::Unknown));
+
+        variables.chain(viewables).collect()
+    }
+}
+
+fn value_from_any(value: &dyn Any) -> Option<Value> {
+    if let Some(v) = value.downcast_ref::<Value>() {
+        Some(v.clone())
+    } else if let Some(v) = value.downcast_ref::<String>() {
+        Some(Value::String(v.clone()))
+    } else if let Some(v) = value.downcast_ref::<i64>() {
+        Some(Value::Integer(*v))
+    } else if let Some(v) = value.downcast_ref::<f64>() {
+        Some(Value::Float(*v))
+    } else if let Some(v) = value.downcast_ref::<bool>() {
+        Some(Value::Bool(*v))
+    } else if let Some(v) = value.downcast_ref::<Vec<Value>>() {
+        Some(Value::List(v.clone()))
+    } else {
+        value
+            .downcast_ref::<BTreeMap<String, Value>>()
+            .map(|v| Value::Map(v.clone()))
     }
 }
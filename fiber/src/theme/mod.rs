@@ -185,6 +185,10 @@ impl Theme {
     //     self.map.iter()
     // }
 
+    pub(crate) fn classes(&self) -> impl Iterator<Item = &String> {
+        self.map.keys()
+    }
+
     #[must_use]
     pub fn get_style(&self, key: &str) -> Option<&Style> {
         self.map.get(key)
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::{ElementId, Node};
+
+/// Identity of a node among its siblings. Nodes with the same key in the old and new tree
+/// are the same node.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NodeKey {
+    /// Value of the `key` attribute
+    Key(String),
+    /// Element name, or `#text` and `#comment`, and the position among siblings with the same
+    /// name
+    Position(String, usize),
+}
+
+impl Display for NodeKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeKey::Key(key) => write!(f, "[key={key}]"),
+            NodeKey::Position(name, i) => write!(f, "{name}[{i}]"),
+        }
+    }
+}
+
+/// Difference between two trees
+#[derive(Debug, PartialEq)]
+pub enum Change<'a> {
+    /// Node, or its attributes, changed. Nodes below it are not compared.
+    Replace {
+        path: Vec<NodeKey>,
+        node: &'a Node<'a>,
+    },
+    /// Children were added, removed or moved. Children kept are compared separately.
+    Children {
+        path: Vec<NodeKey>,
+        children: &'a [Node<'a>],
+    },
+}
+
+/// Keys of the nodes. Repeated `key` values fall back to the position.
+#[must_use]
+pub fn keys(nodes: &[Node]) -> Vec<NodeKey> {
+    let mut counts = HashMap::<&str, usize>::new();
+    let mut explicit = Vec::new();
+
+    nodes
+        .iter()
+        .map(|node| {
+            let name = match node {
+                Node::Element(elem) => {
+                    if let Some(key) = elem.get_attr("key").map(ToString::to_string) {
+                        if !explicit.contains(&key) {
+                            explicit.push(key.clone());
+                            return NodeKey::Key(key);
+                        }
+                    }
+
+                    elem.kind.name()
+                }
+                Node::Text(_) => "#text",
+                Node::Comment(_) | Node::BlockComment(_) => "#comment",
+            };
+
+            let count = counts.entry(name).or_default();
+            let key = NodeKey::Position(name.to_string(), *count);
+            *count += 1;
+            key
+        })
+        .collect()
+}
+
+/// Changes that turn `old` into `new`, outermost first. Paths are keys from the root, the root
+/// itself has an empty path.
+#[must_use]
+pub fn diff<'a>(old: &Node, new: &'a Node<'a>) -> Vec<Change<'a>> {
+    let mut changes = Vec::new();
+    diff_node(old, new, &mut Vec::new(), &mut changes);
+    changes
+}
+
+fn diff_node<'a>(
+    old: &Node,
+    new: &'a Node<'a>,
+    path: &mut Vec<NodeKey>,
+    changes: &mut Vec<Change<'a>>,
+) {
+    let (Node::Element(old_elem), Node::Element(new_elem)) = (old, new) else {
+        if old != new {
+            changes.push(Change::Replace {
+                path: path.clone(),
+                node: new,
+            });
+        }
+        return;
+    };
+
+    if old_elem.kind != new_elem.kind || !old_elem.same_attributes(new_elem) {
+        changes.push(Change::Replace {
+            path: path.clone(),
+            node: new,
+        });
+        return;
+    }
+
+    let old_keys = keys(&old_elem.children);
+    let new_keys = keys(&new_elem.children);
+
+    if old_keys != new_keys {
+        changes.push(Change::Children {
+            path: path.clone(),
+            children: &new_elem.children,
+        });
+    }
+
+    for (key, new_child) in new_keys.into_iter().zip(&new_elem.children) {
+        let Some(i) = old_keys.iter().position(|k| *k == key) else {
+            continue;
+        };
+
+        path.push(key);
+        diff_node(&old_elem.children[i], new_child, path, changes);
+        path.pop();
+    }
+}
+
+/// Node at the path, see `diff`
+#[must_use]
+pub fn node_at<'n, 'a>(root: &'n Node<'a>, path: &[NodeKey]) -> Option<&'n Node<'a>> {
+    let Some((key, rest)) = path.split_first() else {
+        return Some(root);
+    };
+
+    let Node::Element(elem) = root else {
+        return None;
+    };
+
+    let i = keys(&elem.children).iter().position(|k| k == key)?;
+    node_at(&elem.children[i], rest)
+}
+
+/// Gives every element an id from its path, see `ElementId`
+pub(crate) fn assign_ids(nodes: &mut [Node], path: &mut Vec<NodeKey>) {
+    for (key, node) in keys(nodes).into_iter().zip(nodes) {
+        let Node::Element(elem) = node else {
+            continue;
+        };
+
+        path.push(key);
+        elem.id = ElementId::from_path(path);
+        assign_ids(&mut elem.children, path);
+        path.pop();
+    }
+}
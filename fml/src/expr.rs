@@ -0,0 +1,657 @@
+use std::fmt::Display;
+
+use crate::{Value, VariableName};
+
+/// Nesting limit for parentheses, unary operators and ternaries
+const MAX_DEPTH: usize = 64;
+
+/// Expression inside `{...}`, for example `{int:count * 2}` or
+/// `{int:count > 10 ? "many" : "few"}`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Variable(VariableName<'static>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// Handler call in event attributes, like `{remove_item(int:row_id)}`. Can't be evaluated.
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq | BinaryOp::Ne => 3,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 4,
+            BinaryOp::Add | BinaryOp::Sub => 5,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 6,
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        };
+        write!(f, "{op}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    UnknownVariable(String),
+    InvalidOperands {
+        op: String,
+        lhs: Value,
+        rhs: Option<Value>,
+    },
+    DivisionByZero,
+    Call(String),
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::UnknownVariable(name) => write!(f, "Unknown variable '{name}'"),
+            EvalError::InvalidOperands {
+                op,
+                lhs,
+                rhs: Some(rhs),
+            } => write!(f, "Invalid operands for `{op}`: {lhs:?} and {rhs:?}"),
+            EvalError::InvalidOperands { op, lhs, rhs: None } => {
+                write!(f, "Invalid operand for `{op}`: {lhs:?}")
+            }
+            EvalError::DivisionByZero => write!(f, "Division by zero"),
+            EvalError::Call(name) => write!(f, "Call to '{name}' can be used only as handler"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl Expr {
+    /// # Errors
+    /// Returns an error if the input is not a valid expression
+    pub fn parse(input: &str) -> Result<Expr, String> {
+        let tokens = tokenize(input)?;
+        let mut parser = ExprParser {
+            tokens,
+            position: 0,
+            depth: 0,
+        };
+
+        let expr = parser.ternary()?;
+
+        match parser.tokens.get(parser.position) {
+            None => Ok(expr),
+            Some(token) => Err(format!("Unexpected `{token}`")),
+        }
+    }
+
+    /// Variables referenced by the expression
+    #[must_use]
+    pub fn variables(&self) -> Vec<&VariableName<'static>> {
+        let mut vars = Vec::new();
+        self.collect_variables(&mut vars);
+        vars
+    }
+
+    fn collect_variables<'e>(&'e self, vars: &mut Vec<&'e VariableName<'static>>) {
+        match self {
+            Expr::Literal(_) => (),
+            Expr::Variable(name) => vars.push(name),
+            Expr::Unary(_, expr) => expr.collect_variables(vars),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.collect_variables(vars);
+                rhs.collect_variables(vars);
+            }
+            Expr::Ternary(cond, then, otherwise) => {
+                cond.collect_variables(vars);
+                then.collect_variables(vars);
+                otherwise.collect_variables(vars);
+            }
+            Expr::Call(_, args) => {
+                for arg in args {
+                    arg.collect_variables(vars);
+                }
+            }
+        }
+    }
+
+    /// Evaluates the expression. Variables are looked up with `resolve`.
+    ///
+    /// # Errors
+    /// Returns an error if a variable is not found or operands are not valid for an operator
+    pub fn eval<F>(&self, resolve: &F) -> Result<Value, EvalError>
+    where
+        F: Fn(&VariableName) -> Option<Value>,
+    {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Variable(name) => {
+                resolve(name).ok_or_else(|| EvalError::UnknownVariable(name.name.to_string()))
+            }
+            Expr::Unary(op, expr) => {
+                let value = expr.eval(resolve)?;
+                match (op, value) {
+                    (UnaryOp::Not, value) => Ok(Value::Bool(!value.is_truthy())),
+                    (UnaryOp::Neg, Value::Integer(v)) => {
+                        v.checked_neg()
+                            .map(Value::Integer)
+                            .ok_or(EvalError::InvalidOperands {
+                                op: "-".to_string(),
+                                lhs: Value::Integer(v),
+                                rhs: None,
+                            })
+                    }
+                    (UnaryOp::Neg, Value::Float(v)) => Ok(Value::Float(-v)),
+                    (UnaryOp::Neg, value) => Err(EvalError::InvalidOperands {
+                        op: "-".to_string(),
+                        lhs: value,
+                        rhs: None,
+                    }),
+                }
+            }
+            Expr::Binary(BinaryOp::And, lhs, rhs) => Ok(Value::Bool(
+                lhs.eval(resolve)?.is_truthy() && rhs.eval(resolve)?.is_truthy(),
+            )),
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => Ok(Value::Bool(
+                lhs.eval(resolve)?.is_truthy() || rhs.eval(resolve)?.is_truthy(),
+            )),
+            Expr::Binary(op, lhs, rhs) => binary(*op, lhs.eval(resolve)?, rhs.eval(resolve)?),
+            Expr::Ternary(cond, then, otherwise) => {
+                if cond.eval(resolve)?.is_truthy() {
+                    then.eval(resolve)
+                } else {
+                    otherwise.eval(resolve)
+                }
+            }
+            Expr::Call(name, _) => Err(EvalError::Call(name.clone())),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Ternary(..) => 0,
+            Expr::Binary(op, ..) => op.precedence(),
+            Expr::Unary(..) => 7,
+            Expr::Literal(_) | Expr::Variable(_) | Expr::Call(..) => 8,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut std::fmt::Formatter<'_>, min: u8) -> std::fmt::Result {
+        if self.precedence() < min {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+/// Prints the expression back in FML syntax, adding parentheses only where needed
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Literal(Value::String(s)) => write!(f, "\"{}\"", escape(s)),
+            // Whole floats keep the decimal point, otherwise they would parse as integers
+            Expr::Literal(Value::Float(v)) if v.fract() == 0.0 => write!(f, "{v:.1}"),
+            Expr::Literal(value) => write!(f, "{value}"),
+            Expr::Variable(VariableName {
+                name,
+                kind: crate::VariableType::Unknown,
+            }) => write!(f, "{name}"),
+            Expr::Variable(VariableName { name, kind }) => write!(f, "{kind}:{name}"),
+            Expr::Unary(op, expr) => {
+                write!(f, "{}", if *op == UnaryOp::Not { "!" } else { "-" })?;
+                expr.fmt_operand(f, 7)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                lhs.fmt_operand(f, op.precedence())?;
+                write!(f, " {op} ")?;
+                rhs.fmt_operand(f, op.precedence() + 1)
+            }
+            Expr::Ternary(cond, then, otherwise) => {
+                cond.fmt_operand(f, 1)?;
+                write!(f, " ? ")?;
+                then.fmt_operand(f, 1)?;
+                write!(f, " : ")?;
+                otherwise.fmt_operand(f, 0)
+            }
+            Expr::Call(name, args) => {
+                write!(f, "{name}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> Result<Value, EvalError> {
+    let invalid = |lhs: Value, rhs: Value| EvalError::InvalidOperands {
+        op: op.to_string(),
+        lhs,
+        rhs: Some(rhs),
+    };
+
+    match op {
+        BinaryOp::Eq => return Ok(Value::Bool(values_eq(&lhs, &rhs))),
+        BinaryOp::Ne => return Ok(Value::Bool(!values_eq(&lhs, &rhs))),
+        BinaryOp::Add if matches!(lhs, Value::String(_)) || matches!(rhs, Value::String(_)) => {
+            return Ok(Value::String(format!("{lhs}{rhs}")));
+        }
+        _ => (),
+    }
+
+    match (&lhs, &rhs) {
+        (Value::Integer(a), Value::Integer(b)) => {
+            let (a, b) = (*a, *b);
+            let value = match op {
+                BinaryOp::Add => Value::Integer(a.wrapping_add(b)),
+                BinaryOp::Sub => Value::Integer(a.wrapping_sub(b)),
+                BinaryOp::Mul => Value::Integer(a.wrapping_mul(b)),
+                BinaryOp::Div | BinaryOp::Rem if b == 0 => return Err(EvalError::DivisionByZero),
+                BinaryOp::Div => Value::Integer(a.wrapping_div(b)),
+                BinaryOp::Rem => Value::Integer(a.wrapping_rem(b)),
+                BinaryOp::Lt => Value::Bool(a < b),
+                BinaryOp::Le => Value::Bool(a <= b),
+                BinaryOp::Gt => Value::Bool(a > b),
+                BinaryOp::Ge => Value::Bool(a >= b),
+                _ => return Err(invalid(lhs, rhs)),
+            };
+            Ok(value)
+        }
+        (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+            let (a, b) = (as_f64(&lhs), as_f64(&rhs));
+            let value = match op {
+                BinaryOp::Add => Value::Float(a + b),
+                BinaryOp::Sub => Value::Float(a - b),
+                BinaryOp::Mul => Value::Float(a * b),
+                BinaryOp::Div => Value::Float(a / b),
+                BinaryOp::Rem => Value::Float(a % b),
+                BinaryOp::Lt => Value::Bool(a < b),
+                BinaryOp::Le => Value::Bool(a <= b),
+                BinaryOp::Gt => Value::Bool(a > b),
+                BinaryOp::Ge => Value::Bool(a >= b),
+                _ => return Err(invalid(lhs, rhs)),
+            };
+            Ok(value)
+        }
+        (Value::String(a), Value::String(b)) => {
+            let value = match op {
+                BinaryOp::Lt => a < b,
+                BinaryOp::Le => a <= b,
+                BinaryOp::Gt => a > b,
+                BinaryOp::Ge => a >= b,
+                _ => return Err(invalid(lhs, rhs)),
+            };
+            Ok(Value::Bool(value))
+        }
+        _ => Err(invalid(lhs, rhs)),
+    }
+}
+
+/// Numbers are equal by value regardless of integer or float
+fn values_eq(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Integer(_), Value::Float(_)) | (Value::Float(_), Value::Integer(_)) => {
+            (as_f64(lhs) - as_f64(rhs)).abs() < f64::EPSILON
+        }
+        _ => lhs == rhs,
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn as_f64(value: &Value) -> f64 {
+    match value {
+        Value::Integer(v) => *v as f64,
+        Value::Float(v) => *v,
+        _ => 0.0,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExprToken {
+    Number(Value),
+    String(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl Display for ExprToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExprToken::Number(value) => write!(f, "{value}"),
+            ExprToken::String(value) => write!(f, "{value:?}"),
+            ExprToken::Ident(value) => write!(f, "{value}"),
+            ExprToken::Op(op) => write!(f, "{op}"),
+        }
+    }
+}
+
+const OPERATORS: [&str; 21] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "?", ":", "(", ")",
+    ",", "=", "&",
+];
+
+/// Escapes the characters that string literals read back, see `tokenize`
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\\' | '"' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn is_ident_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '.')
+}
+
+fn tokenize(input: &str) -> Result<Vec<ExprToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(i, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+        } else if ch.is_ascii_digit() {
+            let mut end = i;
+            while let Some(&(j, c)) = chars.peek() {
+                if c.is_ascii_digit() || c == '.' {
+                    end = j + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            let number = &input[i..end];
+            let value = if let Ok(v) = number.parse::<i64>() {
+                Value::Integer(v)
+            } else {
+                Value::Float(
+                    number
+                        .parse::<f64>()
+                        .map_err(|_| format!("Invalid number `{number}`"))?,
+                )
+            };
+            tokens.push(ExprToken::Number(value));
+        } else if ch == '"' || ch == '\'' {
+            chars.next();
+            let mut value = String::new();
+            let mut terminated = false;
+
+            while let Some((_, c)) = chars.next() {
+                if c == ch {
+                    terminated = true;
+                    break;
+                }
+                if c == '\\' {
+                    value.push(match chars.next() {
+                        Some((_, 'n')) => '\n',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'r')) => '\r',
+                        Some((_, escaped @ ('\\' | '"' | '\''))) => escaped,
+                        Some((_, escaped)) => return Err(format!("Unknown escape `\\{escaped}`")),
+                        None => break,
+                    });
+                    continue;
+                }
+                value.push(c);
+            }
+
+            if !terminated {
+                return Err("Unterminated string".to_string());
+            }
+            tokens.push(ExprToken::String(value));
+        } else if ch.is_alphabetic() || ch == '_' {
+            let mut end = i;
+            while let Some(&(j, c)) = chars.peek() {
+                // Type prefix is part of the name only when the colon is directly followed by
+                // a name, so `a ? b : c` is still a ternary
+                let typed = c == ':'
+                    && input[j + 1..]
+                        .chars()
+                        .next()
+                        .is_some_and(|n| n.is_alphabetic() || n == '_');
+
+                if is_ident_char(c) || typed {
+                    end = j + c.len_utf8();
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(ExprToken::Ident(input[i..end].to_string()));
+        } else {
+            let rest = &input[i..];
+            let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+                return Err(format!("Unexpected character `{ch}`"));
+            };
+
+            if matches!(*op, "=" | "&") {
+                return Err(format!("Unexpected character `{ch}`"));
+            }
+
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push(ExprToken::Op(op));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<ExprToken>,
+    position: usize,
+    depth: usize,
+}
+
+impl ExprParser {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some(ExprToken::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if self.peek_op() == Some(op) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            Err("Expression is nested too deep".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn ternary(&mut self) -> Result<Expr, String> {
+        let cond = self.binary(1)?;
+
+        if !self.eat("?") {
+            return Ok(cond);
+        }
+
+        self.enter()?;
+        let then = self.ternary()?;
+        if !self.eat(":") {
+            return Err("Expected `:` in ternary".to_string());
+        }
+        let otherwise = self.ternary()?;
+        self.depth -= 1;
+
+        Ok(Expr::Ternary(
+            Box::new(cond),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    /// Precedence climbing for left associative binary operators
+    fn binary(&mut self, min: u8) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+
+        while let Some(op) = self.peek_op().and_then(binary_op) {
+            if op.precedence() < min {
+                break;
+            }
+            self.position += 1;
+
+            let rhs = self.binary(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let op = if self.eat("!") {
+            UnaryOp::Not
+        } else if self.eat("-") {
+            UnaryOp::Neg
+        } else {
+            return self.primary();
+        };
+
+        self.enter()?;
+        let expr = self.unary()?;
+        self.depth -= 1;
+
+        Ok(Expr::Unary(op, Box::new(expr)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let Some(token) = self.tokens.get(self.position).cloned() else {
+            return Err("Unexpected end of expression".to_string());
+        };
+        self.position += 1;
+
+        match token {
+            ExprToken::Number(value) => Ok(Expr::Literal(value)),
+            ExprToken::String(value) => Ok(Expr::Literal(Value::String(value))),
+            ExprToken::Ident(ident) => Ok(match ident.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                _ if self.eat("(") => self.call(ident.clone())?,
+                _ => Expr::Variable(VariableName::from(ident.as_str()).into_owned()),
+            }),
+            ExprToken::Op("(") => {
+                self.enter()?;
+                let expr = self.ternary()?;
+                self.depth -= 1;
+
+                if self.eat(")") {
+                    Ok(expr)
+                } else {
+                    Err("Expected `)`".to_string())
+                }
+            }
+            ExprToken::Op(op) => Err(format!("Unexpected `{op}`")),
+        }
+    }
+
+    fn call(&mut self, name: String) -> Result<Expr, String> {
+        let mut args = Vec::new();
+
+        if self.eat(")") {
+            return Ok(Expr::Call(name, args));
+        }
+
+        self.enter()?;
+        loop {
+            args.push(self.ternary()?);
+
+            if self.eat(")") {
+                break;
+            }
+            if !self.eat(",") {
+                return Err("Expected `,` or `)` in call".to_string());
+            }
+        }
+        self.depth -= 1;
+
+        Ok(Expr::Call(name, args))
+    }
+}
+
+fn binary_op(op: &str) -> Option<BinaryOp> {
+    Some(match op {
+        "||" => BinaryOp::Or,
+        "&&" => BinaryOp::And,
+        "==" => BinaryOp::Eq,
+        "!=" => BinaryOp::Ne,
+        "<" => BinaryOp::Lt,
+        "<=" => BinaryOp::Le,
+        ">" => BinaryOp::Gt,
+        ">=" => BinaryOp::Ge,
+        "+" => BinaryOp::Add,
+        "-" => BinaryOp::Sub,
+        "*" => BinaryOp::Mul,
+        "/" => BinaryOp::Div,
+        "%" => BinaryOp::Rem,
+        _ => return None,
+    })
+}
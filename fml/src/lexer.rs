@@ -1,5 +1,17 @@
 use std::fmt::Display;
 
+use crate::parser::{ParseError, ParseErrorKind};
+
+/// Location of a piece of source. `start` and `end` are byte offsets, `line` and `col` are
+/// 1-based and point to the first character.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
 #[derive(Debug)]
 pub struct Token<'a> {
     pub kind: TokenKind<'a>,
@@ -9,6 +21,18 @@ pub struct Token<'a> {
     pub col: usize,
 }
 
+impl Token<'_> {
+    #[must_use]
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.end,
+            line: self.line,
+            col: self.col,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum TokenKind<'a> {
     TagStart,     // <
@@ -23,6 +47,8 @@ pub enum TokenKind<'a> {
     Expression(&'a str),
     Text(&'a str), // Text content between tags
     LineComment(&'a str),
+    /// Content between <!-- and -->
+    BlockComment(&'a str),
     // EOF,
 }
 
@@ -41,6 +67,7 @@ impl<'a> Display for TokenKind<'a> {
             TokenKind::Expression(expr) => write!(f, "Expression: {expr}"),
             TokenKind::Text(text) => write!(f, "Text content between tags: {text}"),
             TokenKind::LineComment(comment) => write!(f, "LineComment: {comment}"),
+            TokenKind::BlockComment(comment) => write!(f, "BlockComment: {comment}"),
             // TokenKind::EOF => write!(f, "EOF"),
         }
     }
@@ -51,6 +78,7 @@ pub struct Lexer<'a> {
     position: usize,
     line: usize,
     column: usize,
+    errors: Vec<ParseError>,
 }
 
 impl<'a> Lexer<'a> {
@@ -61,9 +89,29 @@ impl<'a> Lexer<'a> {
             position: 0,
             line: 1,
             column: 0,
+            errors: Vec::new(),
         }
     }
 
+    /// Errors found during the last `lex` call
+    #[must_use]
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    #[inline]
+    fn error(&mut self, kind: ParseErrorKind, start: usize, line: usize, col: usize) {
+        self.errors.push(ParseError::new(
+            kind,
+            Span {
+                start,
+                end: self.position,
+                line,
+                col,
+            },
+        ));
+    }
+
     #[inline]
     fn next_char(&mut self) -> Option<char> {
         let ch = self.input[self.position..].chars().next()?;
@@ -99,15 +147,54 @@ impl<'a> Lexer<'a> {
     pub fn lex(&mut self) -> Vec<Token<'a>> {
         let mut tokens = Vec::with_capacity(self.input.len());
         let mut inside_tag = false;
+        // First name inside a tag is the tag name, the rest are attribute names
+        let mut tag_named = false;
         let mut start_pos;
         let mut value_start_pos;
+        let mut line;
+        let mut col;
+
+        self.errors.clear();
+
+        loop {
+            line = self.line;
+            col = self.column + 1;
+
+            let Some(ch) = self.next_char() else {
+                break;
+            };
 
-        while let Some(ch) = self.next_char() {
             start_pos = self.position - ch.len_utf8();
 
             match ch {
+                '<' if self.input[self.position..].starts_with("!--") => {
+                    let content_start = self.position + 3;
+
+                    let content_end = match self.input[content_start..].find("-->") {
+                        Some(end) => content_start + end,
+                        None => {
+                            self.error(ParseErrorKind::UnterminatedComment, start_pos, line, col);
+                            self.input.len()
+                        }
+                    };
+
+                    let end = (content_end + 3).min(self.input.len());
+
+                    while self.position < end {
+                        self.next_char();
+                    }
+
+                    tokens.push(Token {
+                        kind: TokenKind::BlockComment(&self.input[content_start..content_end]),
+                        start: start_pos,
+                        end: self.position,
+                        line,
+                        col,
+                    });
+                }
                 '<' => {
                     inside_tag = true;
+                    tag_named = false;
 
                     if let Some('/') = self.peek_char() {
                         self.next_char();
@@ -116,16 +203,16 @@ impl<'a> Lexer<'a> {
                             kind: TokenKind::TagClose,
                             start: start_pos,
                             end: self.position,
-                            line: self.line,
-                            col: self.column,
+                            line,
+                            col,
                         });
                     } else {
                         tokens.push(Token {
                             kind: TokenKind::TagStart,
                             start: start_pos,
                             end: self.position,
-                            line: self.line,
-                            col: self.column,
+                            line,
+                            col,
                         });
                     }
                 }
@@ -136,8 +223,8 @@ impl<'a> Lexer<'a> {
                         kind: TokenKind::TagEnd,
                         start: start_pos,
                         end: self.position,
-                        line: self.line,
-                        col: self.column,
+                        line,
+                        col,
                     });
                 }
                 '/' => match self.peek_char() {
@@ -148,8 +235,8 @@ impl<'a> Lexer<'a> {
                             kind: TokenKind::TagSelfClose,
                             start: start_pos,
                             end: self.position,
-                            line: self.line,
-                            col: self.column,
+                            line,
+                            col,
                         });
                     }
 
@@ -162,8 +249,8 @@ impl<'a> Lexer<'a> {
                                     ),
                                     start: start_pos,
                                     end: self.position,
-                                    line: self.line,
-                                    col: self.column,
+                                    line,
+                                    col,
                                 });
                                 break;
                             }
@@ -179,43 +266,67 @@ impl<'a> Lexer<'a> {
                     kind: TokenKind::EqualSign,
                     start: start_pos,
                     end: self.position,
-                    line: self.line,
-                    col: self.column,
+                    line,
+                    col,
                 }),
 
                 '{' if inside_tag => {
+                    let mut terminated = false;
+                    let mut quote = None;
+
+                    // Braces inside string literals of an expression don't end it
                     while let Some(next_ch) = self.next_char() {
-                        if next_ch == '}' {
-                            break;
+                        match (quote, next_ch) {
+                            (None, '}') => {
+                                terminated = true;
+                                break;
+                            }
+                            (None, '"' | '\'') => quote = Some(next_ch),
+                            (Some(q), _) if q == next_ch => quote = None,
+                            _ => (),
                         }
                     }
 
+                    let value_end = if terminated {
+                        self.position - 1
+                    } else {
+                        self.error(ParseErrorKind::UnterminatedVariable, start_pos, line, col);
+                        self.position
+                    };
+
+                    let content = &self.input[(start_pos + 1)..value_end];
+
+                    // Plain names become variables when the parser reads the expression
                     tokens.push(Token {
-                        kind: TokenKind::Variable(
-                            &self.input[(start_pos + 1)..(self.position - 1)],
-                        ),
+                        kind: TokenKind::Expression(content),
                         start: start_pos,
                         end: self.position,
-                        line: self.line,
-                        col: self.column,
+                        line,
+                        col,
                     });
                 }
 
-                '"' if inside_tag => {
+                '"' | '\'' if inside_tag => {
                     value_start_pos = self.position;
+                    let mut terminated = false;
 
                     while let Some(next_ch) = self.next_char() {
-                        if next_ch == '"' {
+                        if next_ch == ch {
+                            terminated = true;
                             break;
                         }
                     }
 
+                    if !terminated {
+                        self.error(ParseErrorKind::UnterminatedString, start_pos, line, col);
+                    }
+
                     tokens.push(Token {
                         kind: TokenKind::AttributeValue(&self.input[start_pos..self.position]),
                         start: value_start_pos - 1,
                         end: self.position,
-                        line: self.line,
-                        col: self.column,
+                        line,
+                        col,
                     });
                 }
 
@@ -236,27 +347,31 @@ impl<'a> Lexer<'a> {
                                 }
                             }
 
-                            let end_pos = self.position;
-
-                            if let Some('=') = self.peek_char() {
-                                tokens.push(Token {
-                                    kind: TokenKind::AttributeName(
-                                        &self.input[start_pos..self.position],
-                                    ),
-                                    start: start_pos,
-                                    end: end_pos,
-                                    line: self.line,
-                                    col: self.column,
-                                });
+                            let content = &self.input[start_pos..self.position];
+
+                            // Unquoted word after `=` is a value, like `disabled=false`
+                            let kind = if !tag_named {
+                                tag_named = true;
+                                TokenKind::TagName(content)
+                            } else if matches!(
+                                tokens.last(),
+                                Some(Token {
+                                    kind: TokenKind::EqualSign,
+                                    ..
+                                })
+                            ) {
+                                TokenKind::AttributeValue(content)
                             } else {
-                                tokens.push(Token {
-                                    kind: TokenKind::TagName(&self.input[start_pos..self.position]),
-                                    start: start_pos,
-                                    end: end_pos,
-                                    line: self.line,
-                                    col: self.column,
-                                });
-                            }
+                                TokenKind::AttributeName(content)
+                            };
+
+                            tokens.push(Token {
+                                kind,
+                                start: start_pos,
+                                end: self.position,
+                                line,
+                                col,
+                            });
                         } else if ch.is_numeric() || ch == '.' || ch == '-' {
                             while let Some(next_ch) = self.peek_char() {
                                 if next_ch.is_numeric() || next_ch == '.' {
@@ -272,8 +387,8 @@ impl<'a> Lexer<'a> {
                                 ),
                                 start: start_pos,
                                 end: self.position,
-                                line: self.line,
-                                col: self.column,
+                                line,
+                                col,
                             });
                         } else if !ch.is_whitespace() {
                             while let Some('<') = self.peek_char() {
@@ -284,14 +399,20 @@ impl<'a> Lexer<'a> {
                                 kind: TokenKind::Text(&self.input[start_pos..self.position]),
                                 start: start_pos,
                                 end: self.position,
-                                line: self.line,
-                                col: self.column,
+                                line,
+                                col,
                             });
                         }
                     } else {
+                        // `<` inside braces is a comparison, not a tag
+                        let mut depth = usize::from(ch == '{');
+
                         while let Some(next_ch) = self.peek_char() {
-                            if next_ch == '<' {
-                                break;
+                            match next_ch {
+                                '<' if depth == 0 => break,
+                                '{' => depth += 1,
+                                '}' => depth = depth.saturating_sub(1),
+                                _ => (),
                             }
 
                             // Escape the escape
@@ -306,8 +427,8 @@ impl<'a> Lexer<'a> {
                             kind: TokenKind::Text(self.input[start_pos..self.position].trim()),
                             start: start_pos,
                             end: self.position,
-                            line: self.line,
-                            col: self.column,
+                            line,
+                            col,
                         });
                     }
                 }
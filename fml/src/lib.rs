@@ -1,33 +1,96 @@
 #![allow(dead_code)]
 #![allow(clippy::module_name_repetitions)]
 
+mod diff;
+mod expr;
 mod lexer;
 mod parser;
+mod printer;
+mod validate;
+mod value;
 
-pub use lexer::{Lexer, Token, TokenKind};
+pub use diff::{diff, keys, node_at, Change, NodeKey};
+pub use expr::{BinaryOp, EvalError, Expr, UnaryOp};
+pub use lexer::{Lexer, Span, Token, TokenKind};
 pub use parser::{
-    Attribute, AttributeValue, Element, ElementKind, Node, Parser, TextElement, VariableName,
-    VariableType,
+    Attribute, AttributeValue, Element, ElementId, ElementKind, Node, ParseError, ParseErrorKind,
+    Parser, TextElement, VariableName, VariableType,
 };
+pub use printer::print;
+pub use validate::{Diagnostic, DiagnosticKind, Severity, Validator};
+pub use value::Value;
 
+/// Several top-level elements are returned as children of an implicit fragment
+///
 /// # Errors
-/// Returns an error if the source is not a valid FML
-pub fn parse(source: &str) -> Result<Node, String> {
-    let mut lexer = Lexer::new(source);
-    let tokens = lexer.lex();
-
-    let mut parser = Parser::new(tokens);
-    let nodes = parser.parse()?;
+/// Returns every lexer and parser error found in the source
+pub fn parse(source: &str) -> Result<Node<'_>, Vec<ParseError>> {
+    let mut nodes = parse_nodes(source, Parser::new)?;
 
     if nodes.len() > 1 {
-        eprintln!("There can be only one top-level tag! Using first.");
-    }
+        let spans = nodes
+            .iter()
+            .filter_map(|n| match n {
+                Node::Element(elem) => Some(elem.span),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
 
-    if nodes.is_empty() {
-        eprintln!("Parser returned no nodes");
+        let span = match (spans.first(), spans.last()) {
+            (Some(first), Some(last)) => Span {
+                end: last.end,
+                ..*first
+            },
+            _ => Span::default(),
+        };
+
+        // Top-level elements have ids as children of the root
+        let mut fragment = Element::new("", Vec::new(), nodes, span);
+        fragment.id = ElementId::from_path(&[]);
+
+        return Ok(Node::Element(fragment));
     }
 
-    let first = nodes.into_iter().next().ok_or("No root tag found!")?;
+    nodes.pop().ok_or_else(|| {
+        let span = Span {
+            line: 1,
+            col: 1,
+            ..Span::default()
+        };
+        vec![ParseError::new(ParseErrorKind::NoRootElement, span)]
+    })
+}
+
+/// Formats the source with canonical indentation, attribute order and self-closing tags.
+/// Comments are kept.
+///
+/// # Errors
+/// Returns every lexer and parser error found in the source, or the values that can't be
+/// printed
+pub fn format(source: &str) -> Result<String, Vec<ParseError>> {
+    parse_nodes(source, Parser::with_comments).and_then(|nodes| print(&nodes))
+}
+
+fn parse_nodes<'a>(
+    source: &'a str,
+    parser: fn(Vec<Token<'a>>) -> Parser<'a>,
+) -> Result<Vec<Node<'a>>, Vec<ParseError>> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.lex();
+    let mut errors = lexer.errors().to_vec();
+
+    let nodes = match parser(tokens).parse() {
+        Ok(nodes) => nodes,
+        Err(e) => {
+            errors.extend(e);
+            Vec::new()
+        }
+    };
+
+    if !errors.is_empty() {
+        errors.sort_by_key(|e| e.span().start);
+        return Err(errors);
+    }
 
-    Ok(first)
+    Ok(nodes)
 }
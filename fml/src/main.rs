@@ -0,0 +1,284 @@
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use fml::{Lexer, Node, ParseError, Parser};
+
+const USAGE: &str = "Usage: fml <command> [path]
+
+Commands:
+    check [path]          Check .fml files for errors
+    fmt [--check] [path]  Format .fml files in place. With --check only lists unformatted files
+    tokens [path]         Print tokens of .fml files
+    ast [path]            Print syntax tree of .fml files
+
+Path can be a file or a directory, defaults to ./fiber";
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    let check_only = args.iter().any(|a| a == "--check");
+    let paths = args
+        .iter()
+        .filter(|a| !a.starts_with("--"))
+        .collect::<Vec<_>>();
+
+    let path = match paths.as_slice() {
+        [] => Path::new("./fiber"),
+        [path] => Path::new(path.as_str()),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let files = match fml_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Failed to read {}: {e}", path.display());
+            return ExitCode::from(2);
+        }
+    };
+
+    let run = match command.as_str() {
+        "check" => check,
+        "fmt" if check_only => fmt_check,
+        "fmt" => fmt,
+        "tokens" => tokens,
+        "ast" => ast,
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        _ => {
+            eprintln!("Unknown command '{command}'\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut failed = false;
+
+    for file in files {
+        let source = match std::fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Failed to read {}: {e}", file.display());
+                failed = true;
+                continue;
+            }
+        };
+
+        if !run(&file, &source) {
+            failed = true;
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn check(file: &Path, source: &str) -> bool {
+    match fml::parse(source) {
+        Ok(_) => true,
+        Err(errors) => {
+            report(file, source, &errors);
+            false
+        }
+    }
+}
+
+fn fmt(file: &Path, source: &str) -> bool {
+    let Some(formatted) = format(file, source) else {
+        return false;
+    };
+
+    if formatted == source {
+        return true;
+    }
+
+    match std::fs::write(file, formatted) {
+        Ok(()) => {
+            println!("Formatted {}", file.display());
+            true
+        }
+        Err(e) => {
+            eprintln!("Failed to write {}: {e}", file.display());
+            false
+        }
+    }
+}
+
+/// Fails for files that are not formatted, without writing them
+fn fmt_check(file: &Path, source: &str) -> bool {
+    match format(file, source) {
+        Some(formatted) if formatted == source => true,
+        Some(_) => {
+            println!("Not formatted: {}", file.display());
+            false
+        }
+        None => false,
+    }
+}
+
+/// Formatted source, or `None` after reporting errors. Output that doesn't parse back to the
+/// same document is refused, so a printer bug can't change the file.
+fn format(file: &Path, source: &str) -> Option<String> {
+    let formatted = match fml::format(source) {
+        Ok(formatted) => formatted,
+        Err(errors) => {
+            report(file, source, &errors);
+            return None;
+        }
+    };
+
+    if parse_with_comments(&formatted) != parse_with_comments(source) {
+        eprintln!(
+            "Failed to format {}: formatted source doesn't match the original",
+            file.display()
+        );
+        return None;
+    }
+
+    Some(formatted)
+}
+
+fn parse_with_comments(source: &str) -> Option<Vec<Node<'_>>> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.lex();
+
+    if !lexer.errors().is_empty() {
+        return None;
+    }
+
+    Parser::with_comments(tokens).parse().ok()
+}
+
+fn tokens(file: &Path, source: &str) -> bool {
+    println!("{}", file.display());
+
+    let mut lexer = Lexer::new(source);
+
+    for token in lexer.lex() {
+        println!("{:>5}:{:<4}{}", token.line, token.col, token.kind);
+    }
+
+    if lexer.errors().is_empty() {
+        true
+    } else {
+        report(file, source, lexer.errors());
+        false
+    }
+}
+
+fn ast(file: &Path, source: &str) -> bool {
+    println!("{}", file.display());
+
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.lex();
+
+    match Parser::with_comments(tokens).parse() {
+        Ok(nodes) => {
+            let mut buf = String::new();
+            for node in &nodes {
+                dump(node, &mut buf, 1);
+            }
+            print!("{buf}");
+            true
+        }
+        Err(errors) => {
+            report(file, source, &errors);
+            false
+        }
+    }
+}
+
+fn dump(node: &Node, buf: &mut String, depth: usize) {
+    let spaces = "    ".repeat(depth);
+
+    match node {
+        Node::Element(elem) => {
+            buf.push_str(&format!("{spaces}{:?}", elem.kind));
+
+            for (i, a) in elem.attributes.iter().enumerate() {
+                let separator = if i == 0 { " | " } else { ", " };
+                buf.push_str(&format!("{separator}{}: {:?}", a.name, a.value));
+            }
+
+            buf.push('\n');
+
+            for child in &elem.children {
+                dump(child, buf, depth + 1);
+            }
+        }
+        Node::Text(text) => {
+            buf.push_str(&format!("{spaces}Text {:?}", text.content));
+
+            if !text.variable_refs.is_empty() {
+                let refs = text
+                    .variable_refs
+                    .iter()
+                    .map(|r| r.expr.to_string())
+                    .collect::<Vec<_>>();
+                buf.push_str(&format!(" | Refs: {}", refs.join(", ")));
+            }
+
+            buf.push('\n');
+        }
+        Node::Comment(comment) | Node::BlockComment(comment) => {
+            buf.push_str(&format!("{spaces}Comment {comment:?}\n"));
+        }
+    }
+}
+
+/// Prints errors with the line they point to
+fn report(file: &Path, source: &str, errors: &[ParseError]) {
+    for e in errors {
+        let span = e.span();
+
+        eprintln!(
+            "{}:{}:{}: {}",
+            file.display(),
+            span.line,
+            span.col,
+            e.kind()
+        );
+
+        if let Some(line) = source.lines().nth(span.line.saturating_sub(1)) {
+            eprintln!("    {line}");
+            eprintln!("    {}^", " ".repeat(span.col.saturating_sub(1)));
+        }
+    }
+}
+
+/// The file itself, or all `.fml` files in the directory and its subdirectories
+fn fml_files(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            files.extend(fml_files(&path)?);
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("fml"))
+        {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
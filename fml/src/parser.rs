@@ -1,23 +1,25 @@
 mod attr;
 mod element;
 mod error;
+mod text;
 
 use std::borrow::Cow;
 
-use attr::VariableRef;
 pub use attr::{Attribute, AttributeValue, VariableName, VariableType};
 pub use element::{Element, ElementId, ElementKind, Node, TextElement};
-use regex::Regex;
+pub use error::{ParseError, ParseErrorKind};
+pub(crate) use text::escape_text;
 
-use crate::lexer::{Token, TokenKind};
-
-lazy_static::lazy_static! {
-    static ref VAR_REGEX: Regex = Regex::new(r"\{[^}]*\}").unwrap();
-}
+use crate::lexer::{Span, Token, TokenKind};
+use crate::Expr;
 
 pub struct Parser<'a> {
     tokens: Vec<Token<'a>>,
     position: usize,
+    errors: Vec<ParseError>,
+    open_tags: Vec<&'a str>,
+    /// Comments between the attributes of the opening tag being parsed
+    tag_comments: Vec<Node<'a>>,
 }
 
 impl<'a> Parser<'a> {
@@ -25,19 +27,52 @@ impl<'a> Parser<'a> {
     pub fn new(mut tokens: Vec<Token<'a>>) -> Self {
         tokens.retain(|t| {
             !matches!(
-                t,
-                Token {
-                    kind: TokenKind::LineComment(_),
-                    ..
-                }
+                t.kind,
+                TokenKind::LineComment(_) | TokenKind::BlockComment(_)
             )
         });
 
-        ElementId::reset();
+        Parser::from_tokens(tokens)
+    }
+
+    /// Parser that keeps comments between elements as `Node::Comment` and `Node::BlockComment`,
+    /// used for formatting. Comments between attributes are kept in `Element::comments`.
+    /// Other comments inside tags, like in closing tags, are dropped.
+    #[must_use]
+    pub fn with_comments(mut tokens: Vec<Token<'a>>) -> Self {
+        let mut inside_tag = false;
+        let mut opening_tag = false;
+        let mut in_attributes = false;
+
+        tokens.retain(|t| match t.kind {
+            TokenKind::TagStart | TokenKind::TagClose => {
+                inside_tag = true;
+                opening_tag = t.kind == TokenKind::TagStart;
+                in_attributes = false;
+                true
+            }
+            TokenKind::TagName(_) => {
+                in_attributes = opening_tag;
+                true
+            }
+            TokenKind::TagEnd | TokenKind::TagSelfClose => {
+                inside_tag = false;
+                true
+            }
+            TokenKind::LineComment(_) | TokenKind::BlockComment(_) => !inside_tag || in_attributes,
+            _ => true,
+        });
+
+        Parser::from_tokens(tokens)
+    }
 
+    fn from_tokens(tokens: Vec<Token<'a>>) -> Self {
         Parser {
             tokens,
             position: 0,
+            errors: Vec::new(),
+            open_tags: Vec::new(),
+            tag_comments: Vec::new(),
         }
     }
 
@@ -51,68 +86,245 @@ impl<'a> Parser<'a> {
         self.position += 1;
     }
 
+    /// Span right after the last token, used for errors at the end of input
     #[inline]
-    fn parse_attributes(&mut self) -> Result<Vec<Attribute<'a>>, String> {
-        let mut attributes = Vec::new();
-        let mut line;
-        let mut col;
+    fn eof_span(&self) -> Span {
+        self.tokens.last().map_or_else(Span::default, |t| Span {
+            start: t.end,
+            end: t.end,
+            line: t.line,
+            col: t.col + (t.end - t.start),
+        })
+    }
 
-        while let Some(token) = self.current_token().as_ref() {
-            line = token.line;
-            col = token.col;
+    /// Span from the start of `start` to the end of the last consumed token
+    #[inline]
+    fn span_from(&self, start: Span) -> Span {
+        let end = self
+            .position
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map_or(start.end, |t| t.end);
+
+        Span { end, ..start }
+    }
 
-            match token.kind {
-                TokenKind::AttributeName(attr_name) => {
-                    self.advance();
+    /// Returns the current token if it matches `kind`, otherwise an error describing
+    /// what was `expected`
+    #[inline]
+    fn expect(&self, kind: &TokenKind, expected: &str) -> Result<Span, ParseError> {
+        match self.current_token() {
+            Some(token) if &token.kind == kind => Ok(token.span()),
+            Some(token) => Err(ParseError::expected_token(
+                expected,
+                &token.kind,
+                token.span(),
+            )),
+            None => Err(ParseError::unexpected_eof(expected, self.eof_span())),
+        }
+    }
 
-                    if !matches!(
-                        self.current_token(),
-                        Some(Token {
-                            kind: TokenKind::EqualSign,
-                            ..
-                        })
-                    ) {
-                        return Err(format!("Expected Equal (=): Line {line} Col {col}"));
-                    }
-                    self.advance();
+    #[inline]
+    fn expect_tag_name(&self) -> Result<(&'a str, Span), ParseError> {
+        match self.current_token() {
+            Some(Token {
+                kind: TokenKind::TagName(name),
+                ..
+            }) => Ok((name, self.tokens[self.position].span())),
+            Some(token) => Err(ParseError::expected_token(
+                "tag name",
+                &token.kind,
+                token.span(),
+            )),
+            None => Err(ParseError::unexpected_eof("tag name", self.eof_span())),
+        }
+    }
 
-                    let value =
-                        if let Some(token) = self.current_token() {
-                            match token.kind {
-                                TokenKind::AttributeValue(attr_value) => attr_value,
-                                TokenKind::Variable(var_value) => var_value,
-                                _ => return Err(format!(
-                                    "Expected AttributeValue or Variable: Line {line}, Col {col}"
-                                )),
-                            }
-                            // if let TokenKind::AttributeValue(attr_value) = token.kind {
-                            //     attr_value
-                            // } else {
-                            //     return Err("Expected AttributeValue".to_string());
-                            // }
-                        } else {
-                            return Err(format!("Expected AttributeValue: Line {line} Col {col}"));
-                        };
+    /// Consumes the tag name. Fragments `<>` and `</>` have no name token and get an empty
+    /// name.
+    #[inline]
+    fn tag_name(&mut self) -> Result<(&'a str, Span), ParseError> {
+        if let Some(
+            token @ Token {
+                kind: TokenKind::TagEnd,
+                ..
+            },
+        ) = self.current_token()
+        {
+            let span = Span {
+                end: token.start,
+                ..token.span()
+            };
+            return Ok(("", span));
+        }
 
-                    self.advance();
+        let name = self.expect_tag_name()?;
+        self.advance();
+        Ok(name)
+    }
+
+    /// Skips tokens until the next tag start or tag close so parsing can continue after an
+    /// error. Always moves forward at least one token from `from`.
+    #[inline]
+    fn recover(&mut self, from: usize) {
+        if self.position == from {
+            self.advance();
+        }
+
+        while let Some(token) = self.current_token() {
+            if matches!(token.kind, TokenKind::TagStart | TokenKind::TagClose) {
+                break;
+            }
+            self.advance();
+        }
+    }
+
+    #[inline]
+    fn parse_attribute(&mut self, attr_name: &'a str) -> Result<Attribute<'a>, ParseError> {
+        let name_span = self.tokens[self.position].span();
 
-                    attributes.push(Attribute {
-                        name: Cow::Borrowed(attr_name),
-                        value: AttributeValue::new(value, line, col)?,
-                    });
+        self.advance();
+        self.skip_tag_comments();
+
+        let (line, col) = (name_span.line, name_span.col);
+
+        // Attribute without a value is a true boolean, like `<button disabled>`
+        if self.expect(&TokenKind::EqualSign, "`=`").is_err() {
+            return Ok(Attribute {
+                name: Cow::Borrowed(attr_name),
+                value: AttributeValue::Boolean {
+                    value: true,
+                    line,
+                    col,
+                },
+                span: name_span,
+            });
+        }
+
+        self.advance();
+        self.skip_tag_comments();
+
+        let value = match self.current_token() {
+            Some(Token {
+                kind: TokenKind::Expression(expr),
+                ..
+            }) => match Expr::parse(expr) {
+                // Braces are always a variable, even without type prefix
+                Ok(Expr::Variable(name)) => AttributeValue::Variable { name, line, col },
+                Ok(expr) => AttributeValue::Expression { expr, line, col },
+                Err(e) => {
+                    return Err(ParseError::new(
+                        ParseErrorKind::InvalidExpression(e),
+                        self.tokens[self.position].span(),
+                    ))
                 }
-                _ => break,
+            },
+            Some(Token {
+                kind: TokenKind::AttributeValue(value),
+                ..
+            }) => AttributeValue::new(value, line, col).map_err(|e| {
+                ParseError::new(
+                    ParseErrorKind::InvalidAttributeValue(e),
+                    self.tokens[self.position].span(),
+                )
+            })?,
+            Some(token) => {
+                return Err(ParseError::expected_token(
+                    "attribute value or variable",
+                    &token.kind,
+                    token.span(),
+                ))
+            }
+            None => {
+                return Err(ParseError::unexpected_eof(
+                    "attribute value or variable",
+                    self.eof_span(),
+                ))
             }
+        };
+
+        self.advance();
+
+        Ok(Attribute {
+            name: Cow::Borrowed(attr_name),
+            value,
+            span: self.span_from(name_span),
+        })
+    }
+
+    /// Parses attributes until the end of the opening tag. Invalid attributes are reported and
+    /// skipped, so the rest of the element can still be parsed. Returns the comments between
+    /// the attributes too.
+    #[inline]
+    fn parse_attributes(&mut self) -> (Vec<Attribute<'a>>, Vec<Node<'a>>) {
+        let mut attributes = Vec::new();
+
+        while let Some(token) = self.current_token() {
+            match token.kind {
+                TokenKind::LineComment(_) | TokenKind::BlockComment(_) => self.skip_tag_comments(),
+                TokenKind::AttributeName(attr_name) => match self.parse_attribute(attr_name) {
+                    Ok(attribute) => attributes.push(attribute),
+                    Err(e) => {
+                        self.errors.push(e);
+                        self.skip_attribute();
+                    }
+                },
+                TokenKind::TagEnd
+                | TokenKind::TagSelfClose
+                | TokenKind::TagStart
+                | TokenKind::TagClose => break,
+                _ => {
+                    self.errors.push(ParseError::expected_token(
+                        "attribute name or `>`",
+                        &token.kind,
+                        token.span(),
+                    ));
+                    self.advance();
+                    self.skip_attribute();
+                }
+            }
+        }
+
+        (attributes, std::mem::take(&mut self.tag_comments))
+    }
+
+    /// Moves comments at the current position to the comments of the tag
+    #[inline]
+    fn skip_tag_comments(&mut self) {
+        while let Some(token) = self.current_token() {
+            let comment = match token.kind {
+                TokenKind::LineComment(comment) => comment_node(comment),
+                TokenKind::BlockComment(comment) => Node::BlockComment(Cow::Borrowed(comment)),
+                _ => break,
+            };
+
+            self.tag_comments.push(comment);
+            self.advance();
         }
+    }
 
-        Ok(attributes)
+    /// Skips tokens until the next attribute or the end of the tag
+    #[inline]
+    fn skip_attribute(&mut self) {
+        while let Some(token) = self.current_token() {
+            if matches!(
+                token.kind,
+                TokenKind::AttributeName(_)
+                    | TokenKind::TagEnd
+                    | TokenKind::TagSelfClose
+                    | TokenKind::TagStart
+                    | TokenKind::TagClose
+            ) {
+                break;
+            }
+            self.advance();
+        }
     }
 
     #[inline]
-    fn parse_children(&mut self) -> Result<Vec<Node<'a>>, String> {
+    fn parse_children(&mut self) -> Vec<Node<'a>> {
         let mut children = Vec::with_capacity(20);
 
-        // loop {
         while let Some(token) = self.current_token() {
             match token.kind {
                 TokenKind::TagStart => {
@@ -123,89 +335,52 @@ impl<'a> Parser<'a> {
                     {
                         break;
                     }
-                    children.push(Node::Element(self.parse_element()?));
+
+                    let from = self.position;
+
+                    match self.parse_element() {
+                        Ok(element) => children.push(Node::Element(element)),
+                        Err(e) => {
+                            self.errors.push(e);
+                            self.recover(from);
+                        }
+                    }
                 }
                 TokenKind::Text(text) => {
-                    let variable_refs = VAR_REGEX
-                        .captures_iter(text)
-                        .filter_map(|cap| {
-                            if cap[0].contains("\\}") {
-                                None
-                            } else {
-                                let start = cap.get(0).unwrap().start();
-                                let end = cap.get(0).unwrap().end();
-                                let range = start + 1..end - 1;
-                                let inner_content = &text[range];
-                                let kind = VariableType::from(
-                                    inner_content
-                                        .split_once(':')
-                                        .map(|s| s.0)
-                                        .unwrap_or_default(),
-                                ); // Idiotic
-
-                                Some(VariableRef {
-                                    full_match: &text[start..end],
-                                    start,
-                                    end,
-                                    kind,
-                                })
-                            }
-                        })
-                        .collect::<Vec<_>>();
-
-                    children.push(Node::Text(TextElement {
-                        content: text,
-                        variable_refs,
-                    }));
+                    let span = token.span();
+                    let text = text::parse_text(text, span, &mut self.errors);
+                    children.push(Node::Text(text));
+                    self.advance();
+                }
+                TokenKind::LineComment(comment) => {
+                    children.push(comment_node(comment));
+                    self.advance();
+                }
+                TokenKind::BlockComment(comment) => {
+                    children.push(Node::BlockComment(Cow::Borrowed(comment)));
                     self.advance();
                 }
-                // TokenKind::Variable(name) => {
-                //     println!("skipvar {name}");
-                //     children.push(Node::Text(name));
-                //     self.advance();
-                // }
                 _ => break,
             }
         }
-        // }
 
-        Ok(children)
+        children
     }
 
-    #[allow(clippy::too_many_lines)]
     #[inline]
-    fn parse_element(&mut self) -> Result<Element<'a>, String> {
-        {
-            let token = self.current_token().ok_or("EOF: Expected TagStart")?;
-
-            if !matches!(
-                token,
-                Token {
-                    kind: TokenKind::TagStart,
-                    ..
-                }
-            ) {
-                return Err(format!(
-                    "Expected TagStart: Line {} Col {}",
-                    token.line, token.col
-                ));
-            }
-        }
-
+    fn parse_element(&mut self) -> Result<Element<'a>, ParseError> {
+        let start = self.expect(&TokenKind::TagStart, "`<`")?;
         self.advance();
 
-        let name = {
-            let token = self.current_token().ok_or("EOF: Expected TagName")?;
-            if let TokenKind::TagName(name) = token.kind {
-                name
-            } else {
-                return Err("Expected TagName".to_string());
-            }
-        };
+        let (name, name_span) = self.tag_name()?;
 
-        self.advance();
+        let (attributes, comments) = self.parse_attributes();
 
-        let attributes = self.parse_attributes()?;
+        if name == "else" {
+            return self
+                .parse_else(attributes, start, name_span)
+                .map(|elem| elem.with_comments(comments));
+        }
 
         if let Some(Token {
             kind: TokenKind::TagSelfClose,
@@ -214,108 +389,146 @@ impl<'a> Parser<'a> {
         {
             self.advance();
 
-            return Ok(Element::new(name, attributes, Vec::new()));
+            return Ok(
+                Element::new(name, attributes, Vec::new(), self.span_from(start))
+                    .with_comments(comments),
+            );
         }
 
-        // TagEnd
-        {
-            let token = self.current_token().ok_or("EOF: Expected TagName")?;
+        self.expect(&TokenKind::TagEnd, "`>` or `/>`")?;
+        self.advance();
 
-            if !matches!(
-                token,
-                Token {
-                    kind: TokenKind::TagEnd,
-                    ..
-                }
-            ) {
-                return Err(format!(
-                    "Expected TagEnd: Line {} Col {}",
-                    token.line, token.col
-                ));
-            }
-        }
+        self.open_tags.push(name);
+        let children = self.parse_children();
+        self.open_tags.pop();
 
+        self.expect(&TokenKind::TagClose, &format!("closing tag `</{name}>`"))?;
         self.advance();
 
-        let children = self.parse_children()?;
+        let (close_name, close_span) = self.tag_name()?;
 
-        {
-            let token = self
-                .current_token()
-                .ok_or_else(|| "Unexpected EOF".to_string())?;
-
-            if !matches!(
-                token,
-                Token {
-                    kind: TokenKind::TagClose,
-                    ..
-                }
-            ) {
-                return Err(format!(
-                    "Expected TagClose: Line {} Col {}",
-                    token.line, token.col
-                ));
-            }
+        // Mismatching name is reported but the tag still closes the element, so parsing can
+        // continue as if the name was right
+        if close_name != name {
+            self.errors.push(ParseError::mismatching_closing_tag(
+                name, close_name, close_span,
+            ));
         }
 
+        self.expect(&TokenKind::TagEnd, "`>`")?;
         self.advance();
 
-        if let Some(Token {
-            kind: TokenKind::TagName(close_name),
-            ..
-        }) = self.current_token()
-        {
-            if close_name != &name {
-                return Err(format!(
-                    "Mismatched closing tag: expected {name}, found {close_name}"
-                ));
-            }
-        } else {
-            return Err("Expected TagName".to_string());
+        Ok(Element::new(name, attributes, children, self.span_from(start)).with_comments(comments))
+    }
+
+    /// Parses `else` inside `if`. Everything after `<else>` or `<else/>` belongs to the else
+    /// branch until `</else>` or the closing tag of the `if`.
+    fn parse_else(
+        &mut self,
+        attributes: Vec<Attribute<'a>>,
+        start: Span,
+        name_span: Span,
+    ) -> Result<Element<'a>, ParseError> {
+        if self.open_tags.last() != Some(&"if") {
+            self.errors
+                .push(ParseError::new(ParseErrorKind::ElseWithoutIf, name_span));
         }
 
+        if !matches!(
+            self.current_token(),
+            Some(Token {
+                kind: TokenKind::TagSelfClose,
+                ..
+            })
+        ) {
+            self.expect(&TokenKind::TagEnd, "`>` or `/>`")?;
+        }
         self.advance();
 
+        self.open_tags.push("else");
+        let children = self.parse_children();
+        self.open_tags.pop();
+
+        if let (
+            Some(Token {
+                kind: TokenKind::TagClose,
+                ..
+            }),
+            Some(Token {
+                kind: TokenKind::TagName("else"),
+                ..
+            }),
+        ) = (self.current_token(), self.tokens.get(self.position + 1))
         {
-            let token = self
-                .current_token()
-                .ok_or_else(|| "Unexpected EOF".to_string())?;
-
-            if !matches!(
-                token,
-                Token {
-                    kind: TokenKind::TagEnd,
-                    ..
+            self.advance();
+            self.advance();
+            self.expect(&TokenKind::TagEnd, "`>`")?;
+            self.advance();
+
+            // Else must be the last child so the branches can be split without searching
+            if let Some(token) = self.current_token() {
+                if token.kind != TokenKind::TagClose {
+                    return Err(ParseError::expected_token(
+                        "closing tag `</if>`",
+                        &token.kind,
+                        token.span(),
+                    ));
                 }
-            ) {
-                return Err(format!(
-                    "Expected TagEnd: Line {} Col {}",
-                    token.line, token.col
-                ));
             }
         }
 
-        self.advance();
-
-        Ok(Element::new(name, attributes, children))
+        Ok(Element::new(
+            "else",
+            attributes,
+            children,
+            self.span_from(start),
+        ))
     }
 
-    #[allow(clippy::missing_errors_doc)]
-    pub fn parse(&mut self) -> Result<Vec<Node<'a>>, String> {
+    /// Parses all top-level elements. Parsing recovers from errors and continues from the next
+    /// tag, so every error in the source is reported.
+    ///
+    /// # Errors
+    /// Returns all errors found while parsing
+    pub fn parse(&mut self) -> Result<Vec<Node<'a>>, Vec<ParseError>> {
         let mut nodes = Vec::with_capacity(1);
 
-        loop {
+        while let Some(token) = self.current_token() {
+            match token.kind {
+                TokenKind::LineComment(comment) => {
+                    nodes.push(comment_node(comment));
+                    self.advance();
+                    continue;
+                }
+                TokenKind::BlockComment(comment) => {
+                    nodes.push(Node::BlockComment(Cow::Borrowed(comment)));
+                    self.advance();
+                    continue;
+                }
+                _ => (),
+            }
+
+            let from = self.position;
+
             match self.parse_element() {
                 Ok(element) => nodes.push(Node::Element(element)),
                 Err(e) => {
-                    if e.as_str() != "EOF" {
-                        eprintln!("{e}");
-                    }
-                    break;
+                    self.errors.push(e);
+                    self.recover(from);
                 }
             }
         }
 
-        Ok(nodes)
+        if self.errors.is_empty() {
+            crate::diff::assign_ids(&mut nodes, &mut Vec::new());
+            Ok(nodes)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
     }
 }
+
+/// Comment text without the `//` and surrounding whitespace
+fn comment_node(comment: &str) -> Node<'_> {
+    Node::Comment(Cow::Borrowed(comment.trim_start_matches('/').trim()))
+}
@@ -1,17 +1,37 @@
 use std::borrow::Cow;
 use std::fmt::Display;
 
-use crate::TokenKind;
+use crate::{Expr, Span};
 
 #[derive(Debug, Clone)]
 pub struct Attribute<'a> {
     pub name: Cow<'a, str>,
     pub value: AttributeValue<'a>,
+    /// From the start of the name to the end of the value
+    pub span: Span,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Spans are not compared
+impl PartialEq for Attribute<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.value == other.value
+    }
+}
+
+impl Attribute<'_> {
+    #[must_use]
+    pub fn into_owned(self) -> Attribute<'static> {
+        Attribute {
+            name: Cow::Owned(self.name.into_owned()),
+            value: self.value.into_owned(),
+            span: self.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VariableName<'a> {
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
     pub kind: VariableType,
 }
 
@@ -25,27 +45,54 @@ impl<'a> VariableName<'a> {
     fn kind(&self) -> VariableType {
         self.kind
     }
+
+    #[must_use]
+    pub fn into_owned(self) -> VariableName<'static> {
+        VariableName {
+            name: Cow::Owned(self.name.into_owned()),
+            kind: self.kind,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VariableType {
     String,
     Integer,
     Float,
+    Boolean,
+    List,
+    Map,
+    Prop,
     Unknown,
 }
 
+impl Display for VariableType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VariableType::String => write!(f, "str"),
+            VariableType::Integer => write!(f, "int"),
+            VariableType::Float => write!(f, "dbl"),
+            VariableType::Boolean => write!(f, "bool"),
+            VariableType::List => write!(f, "list"),
+            VariableType::Map => write!(f, "map"),
+            VariableType::Prop => write!(f, "prop"),
+            VariableType::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
 impl<'a> From<&'a str> for VariableName<'a> {
     fn from(value: &'a str) -> Self {
         let Some((t, name)) = value.split_once(':') else {
             return VariableName {
-                name: value,
+                name: Cow::Borrowed(value),
                 kind: VariableType::Unknown,
             };
         };
 
         VariableName {
-            name,
+            name: Cow::Borrowed(name),
             kind: VariableType::from(t),
         }
     }
@@ -57,29 +104,50 @@ impl<'a> From<&'a str> for VariableType {
             "str" => VariableType::String,
             "int" => VariableType::Integer,
             "dbl" => VariableType::Float,
+            "bool" => VariableType::Boolean,
+            "list" => VariableType::List,
+            "map" => VariableType::Map,
+            "prop" => VariableType::Prop,
             _ => VariableType::Unknown,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VariableRef<'a> {
-    pub full_match: &'a str,
+    pub full_match: Cow<'a, str>,
     pub start: usize,
     pub end: usize,
     pub kind: VariableType,
+    pub expr: Expr,
 }
 
 impl VariableRef<'_> {
     pub fn name(&self) -> &'_ str {
-        &self.full_match[self.start + 1..self.end - 1]
+        &self.full_match[1..self.full_match.len() - 1]
+    }
+
+    #[must_use]
+    pub fn variable(&self) -> VariableName<'_> {
+        VariableName::from(self.name())
+    }
+
+    #[must_use]
+    pub fn into_owned(self) -> VariableRef<'static> {
+        VariableRef {
+            full_match: Cow::Owned(self.full_match.into_owned()),
+            start: self.start,
+            end: self.end,
+            kind: self.kind,
+            expr: self.expr,
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum AttributeValue<'a> {
     String {
-        value: &'a str,
+        value: Cow<'a, str>,
         line: usize,
         col: usize,
     },
@@ -93,11 +161,22 @@ pub enum AttributeValue<'a> {
         line: usize,
         col: usize,
     },
+    /// `true` or `false`, or an attribute without a value
+    Boolean {
+        value: bool,
+        line: usize,
+        col: usize,
+    },
     Variable {
         name: VariableName<'a>,
         line: usize,
         col: usize,
     },
+    Expression {
+        expr: Expr,
+        line: usize,
+        col: usize,
+    },
 }
 
 impl Display for AttributeValue<'_> {
@@ -106,7 +185,40 @@ impl Display for AttributeValue<'_> {
             AttributeValue::String { value, .. } => write!(f, "{value}"),
             AttributeValue::Integer { value, .. } => write!(f, "{value}"),
             AttributeValue::Float { value, .. } => write!(f, "{value}"),
+            AttributeValue::Boolean { value, .. } => write!(f, "{value}"),
             AttributeValue::Variable { name, .. } => write!(f, "{name}"),
+            AttributeValue::Expression { expr, .. } => write!(f, "{expr}"),
+        }
+    }
+}
+
+/// Positions are not compared
+impl PartialEq for AttributeValue<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (AttributeValue::String { value: a, .. }, AttributeValue::String { value: b, .. }) => {
+                a == b
+            }
+            (
+                AttributeValue::Integer { value: a, .. },
+                AttributeValue::Integer { value: b, .. },
+            ) => a == b,
+            (AttributeValue::Float { value: a, .. }, AttributeValue::Float { value: b, .. }) => {
+                a == b
+            }
+            (
+                AttributeValue::Boolean { value: a, .. },
+                AttributeValue::Boolean { value: b, .. },
+            ) => a == b,
+            (
+                AttributeValue::Variable { name: a, .. },
+                AttributeValue::Variable { name: b, .. },
+            ) => a == b,
+            (
+                AttributeValue::Expression { expr: a, .. },
+                AttributeValue::Expression { expr: b, .. },
+            ) => a == b,
+            _ => false,
         }
     }
 }
@@ -115,12 +227,32 @@ impl<'a> AttributeValue<'a> {
     /// # Errors
     /// Returns an error if the input is not a valid `AttributeValue`
     #[inline]
-    pub fn new(input: &'a str, line: usize, col: usize) -> Result<AttributeValue, String> {
-        if input.contains(':') {
-            let name = VariableName::from(input.trim_end_matches(['{', '}']));
+    pub fn new(input: &'a str, line: usize, col: usize) -> Result<AttributeValue<'a>, String> {
+        if input.contains('\n') {
+            return Err("Line breaks are not allowed in attribute values".to_string());
+        }
+
+        // Quoted values are always strings. Only the outer quotes are removed, so single-quoted
+        // values can contain double quotes.
+        if let Some(quote) = input.chars().next().filter(|c| matches!(c, '"' | '\'')) {
+            let value = input[1..].strip_suffix(quote).unwrap_or(&input[1..]);
+
+            return Ok(AttributeValue::String {
+                value: Cow::Borrowed(value.trim_matches(' ')),
+                line,
+                col,
+            });
+        }
+
+        if input.contains(':') || (input.starts_with('{') && input.ends_with('}')) {
+            let name = VariableName::from(input.trim_start_matches('{').trim_end_matches('}'));
             return Ok(AttributeValue::Variable { name, line, col });
         }
 
+        if let Ok(value) = input.parse::<bool>() {
+            return Ok(AttributeValue::Boolean { value, line, col });
+        }
+
         if let Ok(value) = input.parse::<i64>() {
             return Ok(AttributeValue::Integer { value, line, col });
         }
@@ -129,33 +261,38 @@ impl<'a> AttributeValue<'a> {
             return Ok(AttributeValue::Float { value, line, col });
         }
 
-        if input.contains('\n') {
-            return Err(format!(
-                "Line breaks are not allowed in attribute values. Line {line}, col {col}"
-            ));
-        }
-
         Ok(AttributeValue::String {
-            value: input.trim_matches(['"', ' ']),
+            value: Cow::Borrowed(input.trim_matches(' ')),
             line,
             col,
         })
     }
 
-    /// # Panics
-    ///  Panics if the token is not valid `AttributeValue`
-    #[inline]
     #[must_use]
-    pub fn from_token(token: &TokenKind<'a>, line: usize, col: usize) -> Self {
-        match token {
-            TokenKind::Variable(value) => AttributeValue::Variable {
-                name: VariableName::from(*value),
+    pub fn into_owned(self) -> AttributeValue<'static> {
+        match self {
+            AttributeValue::String { value, line, col } => AttributeValue::String {
+                value: Cow::Owned(value.into_owned()),
+                line,
+                col,
+            },
+            AttributeValue::Integer { value, line, col } => {
+                AttributeValue::Integer { value, line, col }
+            }
+            AttributeValue::Float { value, line, col } => {
+                AttributeValue::Float { value, line, col }
+            }
+            AttributeValue::Boolean { value, line, col } => {
+                AttributeValue::Boolean { value, line, col }
+            }
+            AttributeValue::Variable { name, line, col } => AttributeValue::Variable {
+                name: name.into_owned(),
                 line,
                 col,
             },
-            TokenKind::AttributeValue(value) => AttributeValue::new(value, line, col).unwrap(),
-            _ => panic!("Invalid token {token:?}, expecting Variable or AttributeValue"),
-            // _ => AttributeValue::String { value: AttributeValue::, line, col }
+            AttributeValue::Expression { expr, line, col } => {
+                AttributeValue::Expression { expr, line, col }
+            }
         }
     }
 }
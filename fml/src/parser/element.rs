@@ -1,37 +1,77 @@
 use std::borrow::Cow;
 use std::fmt::Display;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 use crate::parser::Attribute;
-use crate::AttributeValue;
+use crate::{AttributeValue, NodeKey, Span};
 
 use super::attr::VariableRef;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Node<'a> {
     Element(Element<'a>),
     // Text(&'a str),
     Text(TextElement<'a>),
+    /// Line comment without the `//`. Only kept when parsing with `Parser::with_comments`.
+    Comment(Cow<'a, str>),
+    /// Content of a `<!-- -->` comment as written. Only kept when parsing with
+    /// `Parser::with_comments`.
+    BlockComment(Cow<'a, str>),
+}
+
+impl Node<'_> {
+    /// Converts the node to one that doesn't borrow the source
+    #[must_use]
+    pub fn into_owned(self) -> Node<'static> {
+        match self {
+            Node::Element(e) => Node::Element(e.into_owned()),
+            Node::Text(t) => Node::Text(t.into_owned()),
+            Node::Comment(c) => Node::Comment(Cow::Owned(c.into_owned())),
+            Node::BlockComment(c) => Node::BlockComment(Cow::Owned(c.into_owned())),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct TextElement<'a> {
-    pub content: &'a str,
+    pub content: Cow<'a, str>,
     pub variable_refs: Vec<VariableRef<'a>>,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct ElementId(u64);
-
-pub(crate) static ELEMENT_ID: AtomicU64 = AtomicU64::new(0);
+/// Spans are not compared
+impl PartialEq for TextElement<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.content == other.content && self.variable_refs == other.variable_refs
+    }
+}
 
-impl ElementId {
-    pub fn next() -> Self {
-        ElementId(ELEMENT_ID.fetch_add(1, Ordering::Relaxed))
+impl TextElement<'_> {
+    #[must_use]
+    pub fn into_owned(self) -> TextElement<'static> {
+        TextElement {
+            content: Cow::Owned(self.content.into_owned()),
+            variable_refs: self
+                .variable_refs
+                .into_iter()
+                .map(VariableRef::into_owned)
+                .collect(),
+            span: self.span,
+        }
     }
+}
+
+/// Identity of an element in its document. Made from the keys of the element and its
+/// ancestors, so the same element gets the same id when the source is parsed again, even if
+/// other parts of the document changed. Elements not made by the parser have the default id.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ElementId(u64);
 
-    pub fn reset() {
-        ELEMENT_ID.store(0, Ordering::Relaxed);
+impl ElementId {
+    pub(crate) fn from_path(path: &[NodeKey]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        ElementId(hasher.finish())
     }
 }
 
@@ -47,9 +87,14 @@ pub struct Element<'a> {
     pub kind: ElementKind<'a>,
     pub attributes: Vec<Attribute<'a>>,
     pub children: Vec<Node<'a>>,
+    /// Comments between the attributes of the opening tag. Only kept when parsing with
+    /// `Parser::with_comments`.
+    pub comments: Vec<Node<'a>>,
+    /// From the start of the opening tag to the end of the closing tag
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ElementKind<'a> {
     Root,
     Box,
@@ -62,15 +107,102 @@ pub enum ElementKind<'a> {
     Input,
     Image,
     Empty,
+    If,
+    Else,
+    For,
+    Props,
+    Slot,
+    Import,
+    Component,
+    /// `<>..</>`, children are laid out by the parent
+    Fragment,
     Custom(Cow<'a, str>),
 }
 
+impl ElementKind<'_> {
+    /// Tag name of the element
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            ElementKind::Root => "root",
+            ElementKind::Box => "box",
+            ElementKind::VStack => "vstack",
+            ElementKind::HStack => "hstack",
+            ElementKind::Clip => "clip",
+            ElementKind::List => "list",
+            ElementKind::Label => "label",
+            ElementKind::Button => "button",
+            ElementKind::Input => "input",
+            ElementKind::Image => "image",
+            ElementKind::Empty => "empty",
+            ElementKind::If => "if",
+            ElementKind::Else => "else",
+            ElementKind::For => "for",
+            ElementKind::Props => "props",
+            ElementKind::Slot => "slot",
+            ElementKind::Import => "import",
+            ElementKind::Component => "component",
+            ElementKind::Fragment => "",
+            ElementKind::Custom(name) => name,
+        }
+    }
+
+    #[must_use]
+    pub fn into_owned(self) -> ElementKind<'static> {
+        match self {
+            ElementKind::Root => ElementKind::Root,
+            ElementKind::Box => ElementKind::Box,
+            ElementKind::VStack => ElementKind::VStack,
+            ElementKind::HStack => ElementKind::HStack,
+            ElementKind::Clip => ElementKind::Clip,
+            ElementKind::List => ElementKind::List,
+            ElementKind::Label => ElementKind::Label,
+            ElementKind::Button => ElementKind::Button,
+            ElementKind::Input => ElementKind::Input,
+            ElementKind::Image => ElementKind::Image,
+            ElementKind::Empty => ElementKind::Empty,
+            ElementKind::If => ElementKind::If,
+            ElementKind::Else => ElementKind::Else,
+            ElementKind::For => ElementKind::For,
+            ElementKind::Props => ElementKind::Props,
+            ElementKind::Slot => ElementKind::Slot,
+            ElementKind::Import => ElementKind::Import,
+            ElementKind::Component => ElementKind::Component,
+            ElementKind::Fragment => ElementKind::Fragment,
+            ElementKind::Custom(name) => ElementKind::Custom(Cow::Owned(name.into_owned())),
+        }
+    }
+}
+
+/// Ids, spans and attribute order are not compared, so the same source formatted differently is
+/// equal
+impl PartialEq for Element<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.children == other.children
+            && self.comments == other.comments
+            && self.same_attributes(other)
+    }
+}
+
 impl<'a> Element<'a> {
+    /// Attributes are equal in any order
+    pub(crate) fn same_attributes(&self, other: &Element) -> bool {
+        fn sorted<'a, 'b>(attributes: &'b [Attribute<'a>]) -> Vec<&'b Attribute<'a>> {
+            let mut sorted = attributes.iter().collect::<Vec<_>>();
+            sorted.sort_by(|a, b| a.name.cmp(&b.name));
+            sorted
+        }
+
+        sorted(&self.attributes) == sorted(&other.attributes)
+    }
+
     #[must_use]
     pub fn new(
         name: &'a str,
         attributes: Vec<Attribute<'a>>,
         children: Vec<Node<'a>>,
+        span: Span,
     ) -> Element<'a> {
         let kind = match name.as_bytes() {
             b"root" => ElementKind::Root,
@@ -83,23 +215,68 @@ impl<'a> Element<'a> {
             b"button" => ElementKind::Button,
             b"input" => ElementKind::Input,
             b"image" => ElementKind::Image,
-            b"" => ElementKind::Empty,
+            b"empty" => ElementKind::Empty,
+            b"if" => ElementKind::If,
+            b"else" => ElementKind::Else,
+            b"for" => ElementKind::For,
+            b"props" => ElementKind::Props,
+            b"slot" => ElementKind::Slot,
+            b"import" => ElementKind::Import,
+            b"component" => ElementKind::Component,
+            b"" => ElementKind::Fragment,
             _ => ElementKind::Custom(Cow::Borrowed(name)),
         };
 
         Element {
-            id: ElementId::next(),
+            id: ElementId::default(),
             kind,
             attributes,
             children,
+            comments: Vec::new(),
+            span,
         }
     }
 
     #[must_use]
-    pub fn get_attr(&self, name: &str) -> Option<AttributeValue<'_>> {
+    pub(crate) fn with_comments(self, comments: Vec<Node<'a>>) -> Element<'a> {
+        Element { comments, ..self }
+    }
+
+    #[must_use]
+    pub fn get_attr(&self, name: &str) -> Option<&AttributeValue<'a>> {
         self.attributes
             .iter()
             .find(|a| a.name == name)
-            .map(|a| a.value)
+            .map(|a| &a.value)
+    }
+
+    /// Splits the children of an `if` element to the nodes before `else` and the children of
+    /// `else`
+    #[must_use]
+    pub fn branches(&self) -> (&[Node<'a>], &[Node<'a>]) {
+        match self.children.last() {
+            Some(Node::Element(Element {
+                kind: ElementKind::Else,
+                children,
+                ..
+            })) => (&self.children[..self.children.len() - 1], children),
+            _ => (&self.children, &[]),
+        }
+    }
+
+    #[must_use]
+    pub fn into_owned(self) -> Element<'static> {
+        Element {
+            id: self.id,
+            kind: self.kind.into_owned(),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(Attribute::into_owned)
+                .collect(),
+            children: self.children.into_iter().map(Node::into_owned).collect(),
+            comments: self.comments.into_iter().map(Node::into_owned).collect(),
+            span: self.span,
+        }
     }
 }
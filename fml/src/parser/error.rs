@@ -1,41 +1,135 @@
-use crate::lexer::TokenKind;
+use std::fmt::Display;
 
+use crate::lexer::Span;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParseErrorKind {
-    ExpectedToken(String),
-    MismatchingClosingTag(String),
+    ExpectedToken {
+        expected: String,
+        found: String,
+    },
+    UnexpectedEof {
+        expected: String,
+    },
+    MismatchingClosingTag {
+        expected: String,
+        found: String,
+    },
+    UnterminatedString,
+    UnterminatedVariable,
+    UnterminatedComment,
+    InvalidAttributeValue(String),
+    InvalidExpression(String),
+    ElseWithoutIf,
+    NoRootElement,
+    /// Value that has no FML source, like a string with both kinds of quotes
+    UnprintableValue(String),
 }
 
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::ExpectedToken { expected, found } => {
+                write!(f, "Expected {expected}, found `{found}`")
+            }
+            ParseErrorKind::UnexpectedEof { expected } => {
+                write!(f, "Unexpected end of input, expected {expected}")
+            }
+            ParseErrorKind::MismatchingClosingTag { expected, found } => {
+                write!(
+                    f,
+                    "Mismatching closing tag. Expected `{expected}`, found `{found}`"
+                )
+            }
+            ParseErrorKind::UnterminatedString => write!(f, "Unterminated string"),
+            ParseErrorKind::UnterminatedVariable => write!(f, "Unterminated variable"),
+            ParseErrorKind::UnterminatedComment => write!(f, "Unterminated comment"),
+            ParseErrorKind::InvalidAttributeValue(reason) => {
+                write!(f, "Invalid attribute value: {reason}")
+            }
+            ParseErrorKind::InvalidExpression(reason) => {
+                write!(f, "Invalid expression: {reason}")
+            }
+            ParseErrorKind::ElseWithoutIf => write!(f, "`else` must be a child of `if`"),
+            ParseErrorKind::NoRootElement => write!(f, "No root element found"),
+            ParseErrorKind::UnprintableValue(reason) => {
+                write!(f, "Value can't be printed: {reason}")
+            }
+        }
+    }
+}
+
+/// Error produced by the lexer, the parser or the printer, positioned at the offending source span
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseError {
     kind: ParseErrorKind,
-    line: usize,
-    col: usize,
+    span: Span,
 }
 
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}:{}", self.kind, self.span.line, self.span.col)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 impl ParseError {
     #[inline]
-    pub fn expected_token(
-        expected: &TokenKind,
-        found: &TokenKind,
-        line: usize,
-        col: usize,
-    ) -> Self {
-        ParseError {
-            kind: ParseErrorKind::ExpectedToken(format!(
-                "Expected token `{expected:?}`, found `{found:?}` at {line}:{col}"
-            )),
-            line,
-            col,
-        }
+    #[must_use]
+    pub fn new(kind: ParseErrorKind, span: Span) -> Self {
+        ParseError { kind, span }
     }
 
     #[inline]
-    pub fn mismatching_closing_tag(expected: &str, found: &str, line: usize, col: usize) -> Self {
-        ParseError {
-            kind: ParseErrorKind::MismatchingClosingTag(format!(
-                "Mismatching closing tag. Expected `{expected}`, found `{found}` at {line}:{col}"
-            )),
-            line,
-            col,
-        }
+    pub fn expected_token(expected: impl Into<String>, found: impl Display, span: Span) -> Self {
+        ParseError::new(
+            ParseErrorKind::ExpectedToken {
+                expected: expected.into(),
+                found: found.to_string(),
+            },
+            span,
+        )
+    }
+
+    #[inline]
+    pub fn unexpected_eof(expected: impl Into<String>, span: Span) -> Self {
+        ParseError::new(
+            ParseErrorKind::UnexpectedEof {
+                expected: expected.into(),
+            },
+            span,
+        )
+    }
+
+    #[inline]
+    pub fn mismatching_closing_tag(expected: &str, found: &str, span: Span) -> Self {
+        ParseError::new(
+            ParseErrorKind::MismatchingClosingTag {
+                expected: expected.to_string(),
+                found: found.to_string(),
+            },
+            span,
+        )
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    #[must_use]
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    #[must_use]
+    pub fn col(&self) -> usize {
+        self.span.col
     }
 }
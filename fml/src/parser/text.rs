@@ -0,0 +1,155 @@
+use std::borrow::Cow;
+
+use super::attr::VariableRef;
+use super::element::TextElement;
+use super::error::{ParseError, ParseErrorKind};
+use super::VariableType;
+use crate::lexer::Span;
+use crate::Expr;
+
+/// Characters that can be escaped with `\` in text
+const ESCAPED: [char; 4] = ['{', '}', '<', '\\'];
+
+/// Decodes text content and finds the `{...}` references in it. `\{`, `\}`, `\<` and `\\` are
+/// the characters themselves, and entities like `&lt;`, `&amp;` and `&#123;` are decoded.
+/// Reference positions are in the decoded content.
+pub(super) fn parse_text<'a>(
+    text: &'a str,
+    span: Span,
+    errors: &mut Vec<ParseError>,
+) -> TextElement<'a> {
+    let mut content = String::with_capacity(text.len());
+    let mut variable_refs = Vec::new();
+    let mut i = 0;
+
+    while let Some(ch) = text[i..].chars().next() {
+        let rest = &text[i..];
+
+        match ch {
+            '\\' => match rest[1..].chars().next().filter(|c| ESCAPED.contains(c)) {
+                Some(escaped) => {
+                    content.push(escaped);
+                    i += 1 + escaped.len_utf8();
+                    continue;
+                }
+                None => content.push('\\'),
+            },
+            '&' => {
+                if let Some((decoded, len)) = entity(rest) {
+                    content.push(decoded);
+                    i += len;
+                    continue;
+                }
+                content.push('&');
+            }
+            '{' => {
+                // `{a\}` is not a reference, like before escapes were decoded
+                if let Some(end) = rest.find('}').filter(|end| !rest[..*end].ends_with('\\')) {
+                    let full_match = &rest[..=end];
+                    let inner = &full_match[1..end];
+
+                    match Expr::parse(inner) {
+                        Ok(expr) => variable_refs.push(VariableRef {
+                            full_match: Cow::Borrowed(full_match),
+                            start: content.len(),
+                            end: content.len() + full_match.len(),
+                            kind: VariableType::from(
+                                inner.split_once(':').map(|s| s.0).unwrap_or_default(),
+                            ),
+                            expr,
+                        }),
+                        Err(e) => errors.push(ParseError::new(
+                            ParseErrorKind::InvalidExpression(e),
+                            Span {
+                                start: span.start + i,
+                                end: span.start + i + full_match.len(),
+                                ..span
+                            },
+                        )),
+                    }
+
+                    content.push_str(full_match);
+                    i += full_match.len();
+                    continue;
+                }
+                content.push('{');
+            }
+            _ => content.push(ch),
+        }
+
+        i += ch.len_utf8();
+    }
+
+    TextElement {
+        content: if content == text {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(content)
+        },
+        variable_refs,
+        span,
+    }
+}
+
+/// Text content as it's written in source, with the characters that would be read as markup
+/// escaped
+pub(crate) fn escape_text(text: &TextElement) -> String {
+    let mut out = String::with_capacity(text.content.len());
+    let mut pos = 0;
+
+    for var in &text.variable_refs {
+        escape(&text.content[pos..var.start], &mut out);
+        out.push_str(&text.content[var.start..var.end]);
+        pos = var.end;
+    }
+
+    escape(&text.content[pos..], &mut out);
+
+    out
+}
+
+fn escape(text: &str, out: &mut String) {
+    for (i, ch) in text.char_indices() {
+        let rest = &text[i..];
+
+        match ch {
+            '<' => out.push_str("&lt;"),
+            '{' => out.push_str("\\{"),
+            // Only where it would be read as an escape, or at the end before a closing tag
+            '\\' if rest[1..]
+                .chars()
+                .next()
+                .is_none_or(|c| ESCAPED.contains(&c)) =>
+            {
+                out.push_str("\\\\");
+            }
+            // Only where it would be read as an entity
+            '&' if entity(rest).is_some() => out.push_str("&amp;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+/// Character of the entity at the start of the text, and the length of the entity
+fn entity(text: &str) -> Option<(char, usize)> {
+    let end = text.get(..12).unwrap_or(text).find(';')?;
+    let name = &text[1..end];
+
+    let ch = match name {
+        "lt" => '<',
+        "gt" => '>',
+        "amp" => '&',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        _ => {
+            let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => name.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+
+    Some((ch, end + 1))
+}
@@ -0,0 +1,198 @@
+use crate::parser::escape_text;
+use crate::{Attribute, AttributeValue, Element, Node, ParseError, ParseErrorKind, VariableType};
+
+const INDENT: &str = "    ";
+
+/// Opening tags longer than this are split to one attribute per line
+const MAX_WIDTH: usize = 100;
+
+/// Prints nodes as FML source with canonical indentation, attribute order and self-closing
+/// tags. Parsing the output gives an equal tree.
+///
+/// # Errors
+/// Returns the attributes whose values can't be written so that they parse back the same
+pub fn print(nodes: &[Node]) -> Result<String, Vec<ParseError>> {
+    let mut printer = Printer {
+        out: String::new(),
+        depth: 0,
+        errors: Vec::new(),
+    };
+
+    for node in nodes {
+        printer.node(node);
+    }
+
+    if printer.errors.is_empty() {
+        Ok(printer.out)
+    } else {
+        Err(printer.errors)
+    }
+}
+
+struct Printer {
+    out: String,
+    depth: usize,
+    errors: Vec<ParseError>,
+}
+
+impl Printer {
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Element(elem) => self.element(elem),
+            Node::Text(text) => self.line(&escape_text(text)),
+            Node::Comment(comment) if comment.is_empty() => self.line("//"),
+            Node::Comment(comment) => self.line(&format!("// {comment}")),
+            Node::BlockComment(comment) => self.line(&format!("<!--{comment}-->")),
+        }
+    }
+
+    fn line(&mut self, content: &str) {
+        self.indent();
+        self.out.push_str(content);
+        self.out.push('\n');
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn element(&mut self, elem: &Element) {
+        let name = elem.kind.name();
+        let attributes = sorted(&elem.attributes)
+            .into_iter()
+            .filter_map(|a| {
+                attribute(a)
+                    .map_err(|kind| self.errors.push(ParseError::new(kind, a.span)))
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+
+        let inline = attributes
+            .iter()
+            .fold(format!("<{name}"), |s, a| format!("{s} {a}"));
+        // Comments in the tag need their own lines
+        let split = !elem.comments.is_empty()
+            || attributes.len() > 1 && self.width(&inline) + 3 > MAX_WIDTH;
+
+        match elem.children.as_slice() {
+            // Fragment has no name to self-close
+            [] if name.is_empty() => self.line("<></>"),
+            [] if split => self.split_tag(name, &attributes, &elem.comments, "/>"),
+            [] => self.line(&format!("{inline} />")),
+            [Node::Text(text)]
+                if !split
+                    && !text.content.contains('\n')
+                    && self.width(&inline) + text.content.len() + name.len() + 4 <= MAX_WIDTH =>
+            {
+                self.line(&format!("{inline}>{}</{name}>", escape_text(text)));
+            }
+            children => {
+                if split {
+                    self.split_tag(name, &attributes, &elem.comments, ">");
+                } else {
+                    self.line(&format!("{inline}>"));
+                }
+
+                self.depth += 1;
+                for child in children {
+                    self.node(child);
+                }
+                self.depth -= 1;
+
+                self.line(&format!("</{name}>"));
+            }
+        }
+    }
+
+    /// Opening tag with one attribute per line, and the comments of the tag after them
+    fn split_tag(&mut self, name: &str, attributes: &[String], comments: &[Node], end: &str) {
+        self.line(&format!("<{name}"));
+
+        self.depth += 1;
+        for a in attributes {
+            self.line(a);
+        }
+        for comment in comments {
+            self.node(comment);
+        }
+        self.depth -= 1;
+
+        self.line(end);
+    }
+
+    fn width(&self, content: &str) -> usize {
+        self.depth * INDENT.len() + content.len()
+    }
+}
+
+/// Attributes in canonical order: identifying and control flow attributes first, then props
+/// and values, styles and events last. Order inside a group is kept.
+fn sorted<'a, 'b>(attributes: &'b [Attribute<'a>]) -> Vec<&'b Attribute<'a>> {
+    let group = |name: &str| match name {
+        "id" | "key" | "name" | "src" | "as" | "cond" | "each" | "index" | "items" | "slot" => 0,
+        "class" | "width" | "height" | "gap" | "margin" | "padding" | "color" => 2,
+        _ if name.starts_with("on") => 3,
+        _ => 1,
+    };
+
+    let mut sorted = attributes.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|a| group(&a.name));
+    sorted
+}
+
+fn attribute(attr: &Attribute) -> Result<String, ParseErrorKind> {
+    let value = match &attr.value {
+        AttributeValue::Boolean { value: true, .. } => return Ok(attr.name.to_string()),
+        AttributeValue::Boolean { value: false, .. } => "false".to_string(),
+        AttributeValue::String { value, .. } => quoted(value)?,
+        AttributeValue::Integer { value, .. } => value.to_string(),
+        // Whole floats keep the decimal point, otherwise they would parse as integers
+        AttributeValue::Float { value, .. } if value.fract() == 0.0 => format!("{value:.1}"),
+        AttributeValue::Float { value, .. } => value.to_string(),
+        // Names that can't be written in braces are written unquoted with their type, like
+        // `str:first-name`
+        AttributeValue::Variable { name, .. } if !is_identifier(&name.name) => {
+            if !name
+                .name
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '-' | ':' | '_'))
+            {
+                return Err(ParseErrorKind::UnprintableValue(format!(
+                    "variable name `{}` has characters that are only allowed in braces",
+                    name.name
+                )));
+            }
+
+            format!("{}:{}", name.kind, name.name)
+        }
+        AttributeValue::Variable { name, .. } if name.kind == VariableType::Unknown => {
+            format!("{{{}}}", name.name)
+        }
+        AttributeValue::Variable { name, .. } => format!("{{{}:{}}}", name.kind, name.name),
+        AttributeValue::Expression { expr, .. } => format!("{{{expr}}}"),
+    };
+
+    Ok(format!("{}={value}", attr.name))
+}
+
+/// Single quotes are used when the value contains double quotes. Quoted strings have no
+/// escapes, so a value with both kinds of quotes can't be written.
+fn quoted(value: &str) -> Result<String, ParseErrorKind> {
+    match (value.contains('"'), value.contains('\'')) {
+        (true, true) => Err(ParseErrorKind::UnprintableValue(
+            "strings can't contain both single and double quotes".to_string(),
+        )),
+        (true, false) => Ok(format!("'{value}'")),
+        (false, _) => Ok(format!("\"{value}\"")),
+    }
+}
+
+/// Variable names that read back as the same variable inside braces, like `user.name`
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '.'))
+}
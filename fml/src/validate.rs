@@ -0,0 +1,448 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use crate::{AttributeValue, Element, ElementKind, Expr, Node, Span, VariableName, VariableType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    UnknownHandler(String),
+    UndeclaredVariable(String),
+    TypeMismatch {
+        name: String,
+        declared: VariableType,
+        found: VariableType,
+    },
+    UnknownComponent(String),
+    UnknownProp(String),
+    UnknownClass(String),
+    UnusedHandler(String),
+    UnusedVariable(String),
+}
+
+impl DiagnosticKind {
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::UnknownClass(_)
+            | DiagnosticKind::UnusedHandler(_)
+            | DiagnosticKind::UnusedVariable(_) => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagnosticKind::UnknownHandler(name) => write!(f, "Handler '{name}' not set"),
+            DiagnosticKind::UndeclaredVariable(name) => {
+                write!(f, "Variable '{name}' not declared")
+            }
+            DiagnosticKind::TypeMismatch {
+                name,
+                declared,
+                found,
+            } => write!(
+                f,
+                "Variable '{name}' is used as {found} but declared as {declared}"
+            ),
+            DiagnosticKind::UnknownComponent(name) => write!(f, "Component '{name}' not found"),
+            DiagnosticKind::UnknownProp(name) => write!(f, "Prop '{name}' not declared"),
+            DiagnosticKind::UnknownClass(name) => write!(f, "Class '{name}' not found in styles"),
+            DiagnosticKind::UnusedHandler(name) => write!(f, "Handler '{name}' is never used"),
+            DiagnosticKind::UnusedVariable(name) => write!(f, "Variable '{name}' is never used"),
+        }
+    }
+}
+
+/// Problem found by `Validator`. Unused handlers and variables have no file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub file: Option<String>,
+    /// Attribute, text or element the problem is in
+    pub span: Option<Span>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.file, self.span) {
+            (Some(file), Some(span)) => {
+                write!(f, "{file}:{}:{}: {}", span.line, span.col, self.kind)
+            }
+            (Some(file), None) => write!(f, "{file}: {}", self.kind),
+            (None, _) => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// Checks parsed FML files against the handlers, variables, components and style classes of a
+/// project. Files are given with `validate`, and `finish` adds the handlers and variables no
+/// file used.
+#[derive(Default)]
+pub struct Validator {
+    handlers: HashSet<String>,
+    vars: HashMap<String, VariableType>,
+    components: HashSet<String>,
+    classes: Option<HashSet<String>>,
+    used_handlers: HashSet<String>,
+    used_vars: HashSet<String>,
+    /// Component props used as handler names, by component and prop
+    handler_props: HashSet<(String, String)>,
+    /// Props given on to props of other components, from the component and prop they come from
+    forwarded_props: Vec<((String, String), (String, String))>,
+    /// Strings given to component props, they name handlers if the prop reaches one
+    prop_strings: Vec<((String, String), String)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Names visible in the file being validated
+#[derive(Default)]
+struct Scope {
+    file: String,
+    /// Name of the component being validated, `None` for the main file
+    component: Option<String>,
+    /// Props declared by the component, `None` if the file doesn't declare them
+    props: Option<Vec<String>>,
+    locals: Vec<String>,
+}
+
+impl Validator {
+    #[must_use]
+    pub fn new() -> Self {
+        Validator::default()
+    }
+
+    #[must_use]
+    pub fn handlers<S: Into<String>>(mut self, handlers: impl IntoIterator<Item = S>) -> Self {
+        self.handlers.extend(handlers.into_iter().map(Into::into));
+        self
+    }
+
+    /// Declared variables. Variables with `VariableType::Unknown` are not type checked.
+    #[must_use]
+    pub fn vars<S: Into<String>>(
+        mut self,
+        vars: impl IntoIterator<Item = (S, VariableType)>,
+    ) -> Self {
+        self.vars
+            .extend(vars.into_iter().map(|(name, kind)| (name.into(), kind)));
+        self
+    }
+
+    #[must_use]
+    pub fn components<S: Into<String>>(mut self, components: impl IntoIterator<Item = S>) -> Self {
+        self.components
+            .extend(components.into_iter().map(Into::into));
+        self
+    }
+
+    /// Style classes. Classes are not checked if none are given.
+    #[must_use]
+    pub fn classes<S: Into<String>>(mut self, classes: impl IntoIterator<Item = S>) -> Self {
+        self.classes
+            .get_or_insert_with(HashSet::new)
+            .extend(classes.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn validate(&mut self, file: &str, node: &Node) {
+        self.validate_file(file, None, node);
+    }
+
+    /// Validates the root of a component. Strings given to its props by instances are handler
+    /// names when the prop is used as a handler.
+    pub fn validate_component(&mut self, name: &str, file: &str, node: &Node) {
+        self.validate_file(file, Some(name.to_string()), node);
+    }
+
+    fn validate_file(&mut self, file: &str, component: Option<String>, node: &Node) {
+        let mut scope = Scope {
+            file: file.to_string(),
+            component,
+            props: match node {
+                Node::Element(root) => declared_props(root),
+                _ => None,
+            },
+            locals: Vec::new(),
+        };
+
+        self.node(node, &mut scope);
+    }
+
+    /// Diagnostics of all validated files, with unused handlers and variables
+    #[must_use]
+    pub fn finish(mut self) -> Vec<Diagnostic> {
+        // Props given on to a prop used as a handler are handlers too
+        loop {
+            let reached = self
+                .forwarded_props
+                .iter()
+                .filter(|(from, to)| {
+                    self.handler_props.contains(to) && !self.handler_props.contains(from)
+                })
+                .map(|(from, _)| from.clone())
+                .collect::<Vec<_>>();
+
+            if reached.is_empty() {
+                break;
+            }
+            self.handler_props.extend(reached);
+        }
+
+        for (prop, value) in &self.prop_strings {
+            if self.handler_props.contains(prop) {
+                self.used_handlers.insert(value.clone());
+            }
+        }
+
+        let mut unused_handlers = self
+            .handlers
+            .difference(&self.used_handlers)
+            .cloned()
+            .collect::<Vec<_>>();
+        unused_handlers.sort();
+
+        let mut unused_vars = self
+            .vars
+            .keys()
+            .filter(|name| !self.used_vars.contains(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        unused_vars.sort();
+
+        let unused = unused_handlers
+            .into_iter()
+            .map(DiagnosticKind::UnusedHandler)
+            .chain(unused_vars.into_iter().map(DiagnosticKind::UnusedVariable))
+            .map(|kind| Diagnostic {
+                kind,
+                file: None,
+                span: None,
+            });
+
+        self.diagnostics.extend(unused);
+        self.diagnostics
+    }
+
+    fn report(&mut self, kind: DiagnosticKind, scope: &Scope, span: Option<Span>) {
+        self.diagnostics.push(Diagnostic {
+            kind,
+            file: Some(scope.file.clone()),
+            span,
+        });
+    }
+
+    fn node(&mut self, node: &Node, scope: &mut Scope) {
+        match node {
+            Node::Element(elem) => self.element(elem, scope),
+            Node::Text(text) => {
+                for var_ref in &text.variable_refs {
+                    self.expr(&var_ref.expr, scope, Some(text.span));
+                }
+            }
+            Node::Comment(_) | Node::BlockComment(_) => (),
+        }
+    }
+
+    fn element(&mut self, elem: &Element, scope: &mut Scope) {
+        match &elem.kind {
+            // Import paths and prop defaults are not references
+            ElementKind::Import | ElementKind::Props => return,
+            ElementKind::Component => {
+                let props = std::mem::replace(&mut scope.props, declared_props(elem));
+                let component = std::mem::replace(
+                    &mut scope.component,
+                    elem.get_attr("name").map(ToString::to_string),
+                );
+                for child in &elem.children {
+                    self.node(child, scope);
+                }
+                scope.props = props;
+                scope.component = component;
+                return;
+            }
+            ElementKind::Custom(name) if !self.components.contains(name.as_ref()) => {
+                let kind = DiagnosticKind::UnknownComponent(name.to_string());
+                self.report(kind, scope, Some(elem.span));
+            }
+            _ => (),
+        }
+
+        for attr in &elem.attributes {
+            let span = Some(attr.span);
+
+            match (attr.name.as_ref(), &attr.value) {
+                ("class", AttributeValue::String { value, .. }) => {
+                    if let Some(classes) = &self.classes {
+                        let unknown = value
+                            .split_whitespace()
+                            .filter(|c| !classes.contains(*c))
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>();
+
+                        for class in unknown {
+                            self.report(DiagnosticKind::UnknownClass(class), scope, span);
+                        }
+                    }
+                }
+                (name, value) if name.starts_with("on") => self.handler(value, scope, span),
+                // Names of the loop variables
+                ("as" | "index", _) if matches!(elem.kind, ElementKind::For) => (),
+                ("bind:value", AttributeValue::String { value, .. }) => {
+                    self.variable(&VariableName::from(value.as_ref()), scope, span);
+                }
+                // Handler names can be given to components as strings
+                (prop, AttributeValue::String { value, .. }) => {
+                    if let ElementKind::Custom(component) = &elem.kind {
+                        let prop = (component.to_string(), prop.to_string());
+                        self.prop_strings.push((prop, value.to_string()));
+                    }
+                }
+                (prop, AttributeValue::Variable { name, .. }) => {
+                    if let (ElementKind::Custom(component), Some(from), VariableType::Prop) =
+                        (&elem.kind, &scope.component, name.kind)
+                    {
+                        self.forwarded_props.push((
+                            (from.clone(), name.name.to_string()),
+                            (component.to_string(), prop.to_string()),
+                        ));
+                    }
+                    self.variable(name, scope, span);
+                }
+                (_, AttributeValue::Expression { expr, .. }) => self.expr(expr, scope, span),
+                (
+                    _,
+                    AttributeValue::Integer { .. }
+                    | AttributeValue::Float { .. }
+                    | AttributeValue::Boolean { .. },
+                ) => (),
+            }
+        }
+
+        let locals = scope.locals.len();
+
+        if matches!(elem.kind, ElementKind::For) {
+            let item = elem
+                .get_attr("as")
+                .map_or_else(|| "item".to_string(), ToString::to_string);
+            scope.locals.push(item);
+            scope
+                .locals
+                .extend(elem.get_attr("index").map(ToString::to_string));
+        }
+
+        for child in &elem.children {
+            self.node(child, scope);
+        }
+
+        scope.locals.truncate(locals);
+    }
+
+    fn handler(&mut self, value: &AttributeValue, scope: &mut Scope, span: Option<Span>) {
+        let name = match value {
+            AttributeValue::Variable { name, .. } if name.kind == VariableType::Prop => {
+                if let Some(component) = &scope.component {
+                    let prop = (component.clone(), name.name.to_string());
+                    self.handler_props.insert(prop);
+                }
+                self.variable(name, scope, span);
+                return;
+            }
+            AttributeValue::Variable { name, .. } => name.name.to_string(),
+            AttributeValue::String { value, .. } => value.to_string(),
+            AttributeValue::Expression {
+                expr: Expr::Call(name, args),
+                ..
+            } => {
+                for arg in args {
+                    self.expr(arg, scope, span);
+                }
+                name.clone()
+            }
+            _ => return,
+        };
+
+        if !self.handlers.contains(&name) {
+            self.report(DiagnosticKind::UnknownHandler(name.clone()), scope, span);
+        }
+
+        self.used_handlers.insert(name);
+    }
+
+    fn expr(&mut self, expr: &Expr, scope: &mut Scope, span: Option<Span>) {
+        for var in expr.variables() {
+            self.variable(var, scope, span);
+        }
+    }
+
+    fn variable(&mut self, var: &VariableName, scope: &mut Scope, span: Option<Span>) {
+        let (root, path) = match var.name.split_once('.') {
+            Some((root, path)) => (root, Some(path)),
+            None => (var.name.as_ref(), None),
+        };
+
+        if var.kind == VariableType::Prop {
+            if scope
+                .props
+                .as_ref()
+                .is_some_and(|props| !props.iter().any(|p| p == root))
+            {
+                self.report(DiagnosticKind::UnknownProp(root.to_string()), scope, span);
+            }
+            return;
+        }
+
+        if scope.locals.iter().any(|l| l == root) {
+            return;
+        }
+
+        self.used_vars.insert(root.to_string());
+
+        let Some(declared) = self.vars.get(root).copied() else {
+            self.report(
+                DiagnosticKind::UndeclaredVariable(root.to_string()),
+                scope,
+                span,
+            );
+            return;
+        };
+
+        // Fields of lists and maps can have any type
+        if path.is_none()
+            && !matches!(var.kind, VariableType::Unknown)
+            && !matches!(declared, VariableType::Unknown)
+            && declared != var.kind
+        {
+            self.report(
+                DiagnosticKind::TypeMismatch {
+                    name: root.to_string(),
+                    declared,
+                    found: var.kind,
+                },
+                scope,
+                span,
+            );
+        }
+    }
+}
+
+/// Names declared with `<props>` in the children of the element
+fn declared_props(elem: &Element) -> Option<Vec<String>> {
+    elem.children.iter().find_map(|n| match n {
+        Node::Element(props) if matches!(props.kind, ElementKind::Props) => Some(
+            props
+                .attributes
+                .iter()
+                .map(|a| a.name.to_string())
+                .collect(),
+        ),
+        _ => None,
+    })
+}
@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+use crate::VariableType;
+
+/// Nesting limit for lists and maps
+const MAX_DEPTH: usize = 64;
+
+/// Plain data that can be stored in state and read from FML
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Creates `Value::Map` from key-value pairs
+    pub fn map<K, V>(entries: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+
+    /// Gets nested value with dot separated path, for example `user.name` or `items.0`
+    #[must_use]
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        path.split('.').try_fold(self, |value, key| match value {
+            Value::Map(map) => map.get(key),
+            Value::List(list) => key.parse::<usize>().ok().and_then(|i| list.get(i)),
+            _ => None,
+        })
+    }
+
+    /// Bools are used as is, numbers are true when not zero and strings and collections when
+    /// not empty
+    #[must_use]
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(value) => *value,
+            Value::Integer(value) => *value != 0,
+            Value::Float(value) => *value != 0.0,
+            Value::String(value) => !value.is_empty(),
+            Value::List(list) => !list.is_empty(),
+            Value::Map(map) => !map.is_empty(),
+        }
+    }
+
+    /// Variable type matching the value
+    #[must_use]
+    pub fn kind(&self) -> VariableType {
+        match self {
+            Value::Bool(_) => VariableType::Boolean,
+            Value::Integer(_) => VariableType::Integer,
+            Value::Float(_) => VariableType::Float,
+            Value::String(_) => VariableType::String,
+            Value::List(_) => VariableType::List,
+            Value::Map(_) => VariableType::Map,
+        }
+    }
+}
+
+/// Parses values written in vars files: `true`, `12`, `1.5`, `"text"`, `[1, 2]` and
+/// `{name: "Jane", tags: ["a", "b"]}`. Unquoted words are strings.
+impl FromStr for Value {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut reader = ValueReader {
+            input,
+            chars: input.char_indices().peekable(),
+            depth: 0,
+        };
+
+        let value = reader.value(true)?;
+        reader.skip_whitespace();
+
+        match reader.chars.peek() {
+            None => Ok(value),
+            Some((_, ch)) => Err(format!("Unexpected `{ch}` after value")),
+        }
+    }
+}
+
+struct ValueReader<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    depth: usize,
+}
+
+impl ValueReader<'_> {
+    fn enter(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            Err("Value is nested too deep".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, ch)| ch.is_whitespace()).is_some() {}
+    }
+
+    /// Top level words can contain delimiters, so `str:title:Hello, world` works
+    fn value(&mut self, top_level: bool) -> Result<Value, String> {
+        self.skip_whitespace();
+
+        match self.chars.peek().copied() {
+            Some((_, '[')) => self.list(),
+            Some((_, '{')) => self.map(),
+            Some((_, quote @ ('"' | '\''))) => self.quoted(quote).map(Value::String),
+            Some((start, _)) => {
+                let word = self.word(start, top_level);
+
+                Ok(if let Ok(value) = word.parse::<bool>() {
+                    Value::Bool(value)
+                } else if let Ok(value) = word.parse::<i64>() {
+                    Value::Integer(value)
+                } else if let Ok(value) = word.parse::<f64>() {
+                    Value::Float(value)
+                } else {
+                    Value::String(word.to_string())
+                })
+            }
+            None => Err("Expected value".to_string()),
+        }
+    }
+
+    fn word(&mut self, start: usize, top_level: bool) -> &str {
+        let mut end = start;
+
+        while let Some((i, ch)) = self
+            .chars
+            .next_if(|(_, ch)| top_level || !matches!(ch, ',' | ':' | ']' | '}'))
+        {
+            end = i + ch.len_utf8();
+        }
+
+        self.input[start..end].trim()
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<String, String> {
+        self.chars.next();
+        let mut value = String::new();
+
+        while let Some((_, ch)) = self.chars.next() {
+            match ch {
+                '\\' => {
+                    if let Some((_, escaped)) = self.chars.next() {
+                        value.push(escaped);
+                    }
+                }
+                ch if ch == quote => return Ok(value),
+                ch => value.push(ch),
+            }
+        }
+
+        Err("Unterminated string".to_string())
+    }
+
+    /// Calls `item` for comma separated items until `close`
+    fn items(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<(), String>,
+    ) -> Result<(), String> {
+        self.chars.next();
+
+        loop {
+            self.skip_whitespace();
+
+            if self.chars.next_if(|(_, ch)| *ch == close).is_some() {
+                return Ok(());
+            }
+
+            item(self)?;
+            self.skip_whitespace();
+
+            match self.chars.next() {
+                Some((_, ',')) => (),
+                Some((_, ch)) if ch == close => return Ok(()),
+                Some((_, ch)) => return Err(format!("Expected `,` or `{close}`, found `{ch}`")),
+                None => return Err(format!("Expected `{close}`")),
+            }
+        }
+    }
+
+    fn list(&mut self) -> Result<Value, String> {
+        self.enter()?;
+        let mut list = Vec::new();
+
+        self.items(']', |reader| {
+            list.push(reader.value(false)?);
+            Ok(())
+        })?;
+
+        self.depth -= 1;
+        Ok(Value::List(list))
+    }
+
+    fn map(&mut self) -> Result<Value, String> {
+        self.enter()?;
+        let mut map = BTreeMap::new();
+
+        self.items('}', |reader| {
+            let key = match reader.chars.peek().copied() {
+                Some((_, quote @ ('"' | '\''))) => reader.quoted(quote)?,
+                Some((start, _)) => reader.word(start, false).to_string(),
+                None => return Err("Expected key".to_string()),
+            };
+
+            reader.skip_whitespace();
+            if reader.chars.next_if(|(_, ch)| *ch == ':').is_none() {
+                return Err(format!("Expected `:` after key `{key}`"));
+            }
+
+            map.insert(key, reader.value(false)?);
+            Ok(())
+        })?;
+
+        self.depth -= 1;
+        Ok(Value::Map(map))
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_nested(f, 0)
+    }
+}
+
+impl Value {
+    /// Lists and maps nested deeper than values can be parsed are printed as `[...]` and
+    /// `{...}`
+    fn fmt_nested(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Integer(value) => write!(f, "{value}"),
+            Value::Float(value) => write!(f, "{value}"),
+            Value::String(value) => write!(f, "{value}"),
+            Value::List(_) if depth >= MAX_DEPTH => write!(f, "[...]"),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (i, value) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    value.fmt_nested(f, depth + 1)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(_) if depth >= MAX_DEPTH => write!(f, "{{...}}"),
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key}: ")?;
+                    value.fmt_nested(f, depth + 1)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Integer(i64::from(value))
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Integer(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(value: Vec<T>) -> Self {
+        Value::List(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(value: BTreeMap<String, Value>) -> Self {
+        Value::Map(value)
+    }
+}
+
+/// Conversions back from `Value` return the original value on mismatch. Integers convert
+/// to floats, other types must match exactly.
+macro_rules! try_from_value {
+    ($ty:ty, $($pattern:pat => $value:expr),+) => {
+        impl TryFrom<Value> for $ty {
+            type Error = Value;
+
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                match value {
+                    $($pattern => Ok($value),)+
+                    other => Err(other),
+                }
+            }
+        }
+    };
+}
+
+try_from_value!(bool, Value::Bool(v) => v);
+try_from_value!(i64, Value::Integer(v) => v);
+try_from_value!(f64, Value::Float(v) => v, Value::Integer(v) => i64_to_f64(v));
+try_from_value!(String, Value::String(v) => v);
+try_from_value!(Vec<Value>, Value::List(v) => v);
+try_from_value!(BTreeMap<String, Value>, Value::Map(v) => v);
+
+#[allow(clippy::cast_precision_loss)]
+fn i64_to_f64(value: i64) -> f64 {
+    value as f64
+}
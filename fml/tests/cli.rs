@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+fn fml(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_fml"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn temp_file(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn cli_check() {
+    let output = fml(&["check", "./tests/data/small.fml"]);
+    assert!(output.status.success());
+
+    let broken = temp_file("broken.fml", "<vstack>\n    <label>Text</vstack>\n");
+    let output = fml(&["check", broken.to_str().unwrap()]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(stderr.contains("broken.fml:2:"), "{stderr}");
+
+    assert_eq!(fml(&["unknown"]).status.code(), Some(2));
+}
+
+#[test]
+fn cli_fmt() {
+    let file = temp_file(
+        "unformatted.fml",
+        "<vstack>\n<label>Text</label>\n</vstack>",
+    );
+
+    assert!(!fml(&["fmt", "--check", file.to_str().unwrap()])
+        .status
+        .success());
+    assert!(fml(&["fmt", file.to_str().unwrap()]).status.success());
+    assert!(fml(&["fmt", "--check", file.to_str().unwrap()])
+        .status
+        .success());
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "<vstack>\n    <label>Text</label>\n</vstack>\n"
+    );
+}
+
+#[test]
+fn cli_dump() {
+    let file = temp_file(
+        "dump.fml",
+        "<vstack class=\"app\">\n    <label>{int:count}</label>\n</vstack>\n",
+    );
+
+    let tokens = String::from_utf8(fml(&["tokens", file.to_str().unwrap()]).stdout).unwrap();
+    assert!(tokens.contains("TagName: vstack"), "{tokens}");
+
+    let ast = String::from_utf8(fml(&["ast", file.to_str().unwrap()]).stdout).unwrap();
+    assert!(ast.contains("VStack | class:"), "{ast}");
+    assert!(ast.contains("Refs: int:count"), "{ast}");
+}
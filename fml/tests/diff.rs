@@ -0,0 +1,105 @@
+use fml::{Change, Node, NodeKey};
+
+fn key(name: &str, i: usize) -> NodeKey {
+    NodeKey::Position(name.to_string(), i)
+}
+
+#[test]
+fn diff_keys() {
+    let root = fml::parse(
+        r#"<vstack><label>A</label><input key="name"/><label>B</label><input key="name"/></vstack>"#,
+    )
+    .unwrap();
+    let Node::Element(root) = root else {
+        unreachable!()
+    };
+
+    assert_eq!(
+        fml::keys(&root.children),
+        vec![
+            key("label", 0),
+            NodeKey::Key("name".to_string()),
+            key("label", 1),
+            key("input", 0),
+        ]
+    );
+}
+
+#[test]
+fn diff_changes() {
+    let old = fml::parse(
+        r#"<root><vstack><label>Count</label><input key="a"/><input key="b"/></vstack><hstack gap=4/></root>"#,
+    )
+    .unwrap();
+
+    // Same tree with attributes in other order
+    let same = fml::parse(
+        r#"<root>
+            <vstack>
+                <label>Count</label>
+                <input key="a"/>
+                <input key="b"/>
+            </vstack>
+            <hstack gap=4/>
+        </root>"#,
+    )
+    .unwrap();
+    assert!(fml::diff(&old, &same).is_empty());
+
+    let changed = fml::parse(
+        r#"<root><vstack><label>Total</label><input key="a"/><input key="b"/></vstack><hstack gap=8/></root>"#,
+    )
+    .unwrap();
+    let changes = fml::diff(&old, &changed);
+    let paths = changes
+        .iter()
+        .map(|c| match c {
+            Change::Replace { path, .. } => path.clone(),
+            Change::Children { .. } => panic!("Unexpected change {c:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            vec![key("vstack", 0), key("label", 0), key("#text", 0)],
+            vec![key("hstack", 0)],
+        ]
+    );
+
+    // Keyed children keep their identity when moved
+    let moved = fml::parse(
+        r#"<root><vstack><label>Count</label><input key="b"/><input key="a" value="x"/></vstack><hstack gap=4/></root>"#,
+    )
+    .unwrap();
+    let changes = fml::diff(&old, &moved);
+    assert_eq!(changes.len(), 2);
+    assert!(matches!(
+        &changes[0],
+        Change::Children { path, children } if *path == [key("vstack", 0)] && children.len() == 3
+    ));
+    assert!(matches!(
+        &changes[1],
+        Change::Replace { path, .. }
+            if *path == [key("vstack", 0), NodeKey::Key("a".to_string())]
+    ));
+
+    let replaced = fml::parse(r"<root><hstack/></root>").unwrap();
+    let changes = fml::diff(&old, &replaced);
+    assert!(matches!(&changes[0], Change::Children { path, .. } if path.is_empty()));
+    assert!(matches!(&changes[1], Change::Replace { path, .. } if *path == [key("hstack", 0)]));
+}
+
+#[test]
+fn diff_node_at() {
+    let root =
+        fml::parse(r#"<root><vstack><label>A</label><box key="b"/></vstack></root>"#).unwrap();
+
+    let path = [key("vstack", 0), NodeKey::Key("b".to_string())];
+    let Some(Node::Element(elem)) = fml::node_at(&root, &path) else {
+        panic!("Node not found");
+    };
+    assert_eq!(elem.kind.name(), "box");
+
+    assert_eq!(fml::node_at(&root, &[]), Some(&root));
+    assert_eq!(fml::node_at(&root, &[key("hstack", 0)]), None);
+}
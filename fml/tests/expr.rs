@@ -0,0 +1,176 @@
+use fml::{
+    AttributeValue, BinaryOp, EvalError, Expr, Lexer, Node, Parser, UnaryOp, Value, VariableName,
+};
+
+fn eval(input: &str) -> Result<Value, EvalError> {
+    let resolve = |var: &VariableName| match var.name.as_ref() {
+        "count" => Some(Value::Integer(12)),
+        "price" => Some(Value::Float(2.5)),
+        "name" => Some(Value::from("fiber")),
+        "user.admin" => Some(Value::Bool(true)),
+        "min" => Some(Value::Integer(i64::MIN)),
+        _ => None,
+    };
+
+    Expr::parse(input).unwrap().eval(&resolve)
+}
+
+#[test]
+fn expr_eval() {
+    assert_eq!(eval("1 + 2 * 3"), Ok(Value::Integer(7)));
+    assert_eq!(eval("(1 + 2) * 3"), Ok(Value::Integer(9)));
+    assert_eq!(eval("int:count * 2"), Ok(Value::Integer(24)));
+    assert_eq!(eval("count * price"), Ok(Value::Float(30.0)));
+    assert_eq!(eval("-count % 5"), Ok(Value::Integer(-2)));
+    assert_eq!(
+        eval("\"hello \" + str:name"),
+        Ok(Value::from("hello fiber"))
+    );
+    assert_eq!(
+        eval("int:count > 10 ? \"many\" : \"few\""),
+        Ok(Value::from("many"))
+    );
+    assert_eq!(eval("count == 12.0 && !false"), Ok(Value::Bool(true)));
+    assert_eq!(eval("user.admin || missing"), Ok(Value::Bool(true)));
+    assert_eq!(eval("count / 0"), Err(EvalError::DivisionByZero));
+    assert_eq!(
+        eval("missing + 1"),
+        Err(EvalError::UnknownVariable("missing".to_string()))
+    );
+    assert!(eval("name * 2").is_err());
+    assert!(matches!(
+        eval("-min"),
+        Err(EvalError::InvalidOperands { rhs: None, .. })
+    ));
+}
+
+#[test]
+fn expr_string_escapes() {
+    assert_eq!(
+        eval(r#""a\nb\tc\\d\"e\'f""#),
+        Ok(Value::from("a\nb\tc\\d\"e'f"))
+    );
+    assert!(Expr::parse(r#""\x""#).is_err());
+
+    let expr = Expr::parse(r#""line\n\"quoted\" \\ end""#).unwrap();
+    assert_eq!(expr.to_string(), r#""line\n\"quoted\" \\ end""#);
+    assert_eq!(Expr::parse(&expr.to_string()).unwrap(), expr);
+}
+
+#[test]
+fn expr_parse_errors() {
+    assert!(Expr::parse("1 +").is_err());
+    assert!(Expr::parse("(1 + 2").is_err());
+    assert!(Expr::parse("a ? b").is_err());
+    assert!(Expr::parse("a = b").is_err());
+    assert!(Expr::parse("1 2").is_err());
+    assert!(Expr::parse(&"(".repeat(100)).is_err());
+}
+
+#[test]
+fn expr_display() {
+    for input in [
+        "int:count * 2",
+        "(a + b) * c",
+        "a - (b - c)",
+        "!(a && b) || c",
+        "a ? b : c ? d : e",
+        "\"text\" + prop:title",
+    ] {
+        let expr = Expr::parse(input).unwrap();
+        assert_eq!(expr.to_string(), input);
+        assert_eq!(Expr::parse(&expr.to_string()).unwrap(), expr);
+    }
+}
+
+#[test]
+fn expr_in_fml() {
+    let input = r#"<root><if cond={int:count > 1}><label>{int:count < 10 ? "few" : "many"}</label></if></root>"#;
+
+    let mut lexer = Lexer::new(input);
+    let tokens = lexer.lex();
+    let mut parser = Parser::new(tokens);
+    let nodes = parser.parse().unwrap();
+
+    let Node::Element(root) = &nodes[0] else {
+        panic!("Expected root element");
+    };
+    let Node::Element(cond) = &root.children[0] else {
+        panic!("Expected if element");
+    };
+
+    assert!(matches!(
+        cond.get_attr("cond"),
+        Some(AttributeValue::Expression { .. })
+    ));
+
+    let Node::Element(label) = &cond.children[0] else {
+        panic!("Expected label element");
+    };
+    let Node::Text(text) = &label.children[0] else {
+        panic!("Expected text");
+    };
+
+    assert_eq!(text.variable_refs.len(), 1);
+    assert_eq!(
+        text.variable_refs[0].expr.to_string(),
+        "int:count < 10 ? \"few\" : \"many\""
+    );
+}
+
+#[test]
+fn expr_negation_in_attributes() {
+    let node = fml::parse(
+        "<box neg={-int:count} lit={-1} sub={int:count-1} name={str:title} dotted={user.name}/>",
+    )
+    .unwrap();
+
+    let Node::Element(elem) = &node else {
+        panic!("Expected box element");
+    };
+    let expr = |name: &str| match elem.get_attr(name) {
+        Some(AttributeValue::Expression { expr, .. }) => expr.clone(),
+        other => panic!("Expected expression for {name}, found {other:?}"),
+    };
+
+    assert!(matches!(
+        expr("neg"),
+        Expr::Unary(UnaryOp::Neg, inner) if matches!(*inner, Expr::Variable(_))
+    ));
+    assert_eq!(expr("lit").eval(&|_| None), Ok(Value::Integer(-1)));
+    assert!(matches!(expr("sub"), Expr::Binary(BinaryOp::Sub, _, _)));
+
+    for name in ["name", "dotted"] {
+        assert!(matches!(
+            elem.get_attr(name),
+            Some(AttributeValue::Variable { .. })
+        ));
+    }
+}
+
+#[test]
+fn expr_invalid_in_fml() {
+    let errors = fml::parse("<root>\n  <label>{count +}</label>\n</root>").unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line(), 2);
+}
+
+#[test]
+fn expr_call() {
+    let expr = Expr::parse("remove_item(int:row_id, \"all\", count + 1)").unwrap();
+
+    let Expr::Call(name, args) = &expr else {
+        panic!("Expected call");
+    };
+    assert_eq!(name, "remove_item");
+    assert_eq!(args.len(), 3);
+    assert_eq!(expr.variables().len(), 2);
+    assert_eq!(
+        expr.to_string(),
+        "remove_item(int:row_id, \"all\", count + 1)"
+    );
+    assert_eq!(Expr::parse("save()").unwrap().to_string(), "save()");
+    assert!(Expr::parse("save(1,)").is_err());
+    assert_eq!(eval("count(1)"), Err(EvalError::Call("count".to_string())));
+}
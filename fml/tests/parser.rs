@@ -1,7 +1,12 @@
-use fml::{Element, Lexer, Node, Parser};
+use std::collections::HashSet;
+
+use fml::{
+    AttributeValue, Element, ElementId, ElementKind, Lexer, Node, ParseErrorKind, Parser,
+    VariableType,
+};
 
 fn iter_ast(node: &Node, buf: &mut String, depth: &mut usize) {
-    let spaces = (0..*depth).into_iter().fold(String::new(), |mut s, _| {
+    let spaces = (0..*depth).fold(String::new(), |mut s, _| {
         s.push_str("    ");
         s
     });
@@ -12,6 +17,8 @@ fn iter_ast(node: &Node, buf: &mut String, depth: &mut usize) {
             kind,
             attributes,
             children,
+            comments: _,
+            span: _,
         }) => {
             let attrs = attributes.iter().fold(String::new(), |mut s, a| {
                 s.push_str(&format!("{}: {:?} ", a.name, a.value));
@@ -48,11 +55,13 @@ fn iter_ast(node: &Node, buf: &mut String, depth: &mut usize) {
 
             buf.push('\n');
         }
+        Node::Comment(comment) => buf.push_str(&format!("{spaces}// {comment}\n")),
+        Node::BlockComment(comment) => buf.push_str(&format!("{spaces}<!--{comment}-->\n")),
     }
 }
 
 fn lex_and_parse(input: &str, name: &str) -> Result<(), String> {
-    let mut lexer = Lexer::new(&input);
+    let mut lexer = Lexer::new(input);
     let tokens = lexer.lex();
 
     let mut parser = Parser::new(tokens);
@@ -90,3 +99,326 @@ fn parse_huge() {
     let input = std::fs::read_to_string("./tests/data/huge.fml").unwrap();
     assert!(lex_and_parse(&input, "huge").is_ok());
 }
+
+#[test]
+fn error_position() {
+    let errors = fml::parse("<vstack>\n    <label>Text</hstack>\n</vstack>").unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].kind(),
+        &ParseErrorKind::MismatchingClosingTag {
+            expected: "label".to_string(),
+            found: "hstack".to_string()
+        }
+    );
+    assert_eq!((errors[0].line(), errors[0].col()), (2, 18));
+    assert_eq!(errors[0].span().start, 26);
+}
+
+#[test]
+fn error_recovery() {
+    let input =
+        "<vstack>\n    <label =1/>\n    <button 5></button>\n    <label>Ok</label>\n</vstack>";
+    let errors = fml::parse(input).unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!((errors[0].line(), errors[0].col()), (2, 12));
+    assert_eq!((errors[1].line(), errors[1].col()), (3, 13));
+}
+
+#[test]
+fn error_unterminated() {
+    let errors = fml::parse("<label class=\"abc />").unwrap_err();
+
+    assert!(errors
+        .iter()
+        .any(|e| e.kind() == &ParseErrorKind::UnterminatedString));
+
+    let errors = fml::parse("<vstack>").unwrap_err();
+
+    assert!(matches!(
+        errors[0].kind(),
+        ParseErrorKind::UnexpectedEof { .. }
+    ));
+}
+
+#[test]
+fn parse_if_else() {
+    let input = "<if cond={bool:logged_in}>\n    <label>Hello</label>\n<else>\n    <label>Log in</label>\n    <button>Login</button>\n</if>";
+    let Node::Element(elem) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+
+    let (then_branch, else_branch) = elem.branches();
+    assert!(matches!(elem.kind, ElementKind::If));
+    assert_eq!(then_branch.len(), 1);
+    assert_eq!(else_branch.len(), 2);
+
+    let input = "<if cond={bool:ok}><label>Yes</label><else><label>No</label></else></if>";
+    let Node::Element(elem) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+
+    assert_eq!(elem.branches().0.len(), 1);
+    assert_eq!(elem.branches().1.len(), 1);
+
+    let errors = fml::parse("<vstack><else/></vstack>").unwrap_err();
+    assert_eq!(errors[0].kind(), &ParseErrorKind::ElseWithoutIf);
+}
+
+#[test]
+fn parse_props() {
+    let input = "<hstack>\n    <props title=\"Counter\" count=0 />\n    <label>{prop:title}</label>\n</hstack>";
+    let Node::Element(elem) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+
+    let Node::Element(props) = &elem.children[0] else {
+        panic!("Expected props");
+    };
+    assert!(matches!(props.kind, ElementKind::Props));
+    assert_eq!(props.attributes.len(), 2);
+
+    let Node::Element(label) = &elem.children[1] else {
+        panic!("Expected label");
+    };
+    let Node::Text(text) = &label.children[0] else {
+        panic!("Expected text");
+    };
+    let var = text.variable_refs[0].variable();
+    assert_eq!(var.kind, VariableType::Prop);
+    assert_eq!(var.name, "title");
+}
+
+#[test]
+fn parse_imports() {
+    let input = "<vstack>\n    <import src=\"widgets/forms.fml\" as=\"forms\" />\n    <forms:field label=\"Name\"></forms:field>\n</vstack>";
+    let Node::Element(elem) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+
+    let Node::Element(import) = &elem.children[0] else {
+        panic!("Expected import");
+    };
+    assert!(matches!(import.kind, ElementKind::Import));
+    assert_eq!(
+        import.get_attr("src").unwrap().to_string(),
+        "widgets/forms.fml"
+    );
+    assert_eq!(import.get_attr("as").unwrap().to_string(), "forms");
+
+    let Node::Element(field) = &elem.children[1] else {
+        panic!("Expected component");
+    };
+    assert!(matches!(&field.kind, ElementKind::Custom(name) if name == "forms:field"));
+
+    let input =
+        "<module>\n    <component name=\"field\">\n        <hstack />\n    </component>\n</module>";
+    let Node::Element(elem) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+
+    let Node::Element(component) = &elem.children[0] else {
+        panic!("Expected component");
+    };
+    assert!(matches!(component.kind, ElementKind::Component));
+    assert_eq!(component.get_attr("name").unwrap().to_string(), "field");
+}
+
+#[test]
+fn parse_spans() {
+    let input =
+        "<vstack>\n    <label class=\"title\">Text</label>\n    <box width={int:w} />\n</vstack>";
+    let Node::Element(root) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+    assert_eq!((root.span.start, root.span.end), (0, input.len()));
+
+    let Node::Element(label) = &root.children[0] else {
+        panic!("Expected label");
+    };
+    assert_eq!((label.span.line, label.span.col), (2, 5));
+    assert_eq!(
+        &input[label.span.start..label.span.end],
+        "<label class=\"title\">Text</label>"
+    );
+
+    let attr = &label.attributes[0];
+    assert_eq!(&input[attr.span.start..attr.span.end], "class=\"title\"");
+    assert_eq!((attr.span.line, attr.span.col), (2, 12));
+
+    let Node::Text(text) = &label.children[0] else {
+        panic!("Expected text");
+    };
+    assert_eq!(&input[text.span.start..text.span.end], "Text");
+
+    let Node::Element(boxed) = &root.children[1] else {
+        panic!("Expected box");
+    };
+    assert_eq!(
+        &input[boxed.span.start..boxed.span.end],
+        "<box width={int:w} />"
+    );
+    assert_eq!(
+        &input[boxed.attributes[0].span.start..boxed.attributes[0].span.end],
+        "width={int:w}"
+    );
+}
+
+#[test]
+fn parse_escapes() {
+    let input = r"<vstack><label>a &lt; b &amp;&amp; c &gt; d &#123;&#x7D; \{int:count} \\ {int:count} & x</label></vstack>";
+    let Node::Element(root) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+    let Node::Element(label) = &root.children[0] else {
+        panic!("Expected label");
+    };
+    let Node::Text(text) = &label.children[0] else {
+        panic!("Expected text");
+    };
+
+    assert_eq!(
+        text.content,
+        r"a < b && c > d {} {int:count} \ {int:count} & x"
+    );
+    assert_eq!(text.variable_refs.len(), 1);
+
+    let var = &text.variable_refs[0];
+    assert_eq!(&text.content[var.start..var.end], "{int:count}");
+    assert_eq!(var.start, text.content.rfind('{').unwrap());
+}
+
+#[test]
+fn parse_block_comments() {
+    let input =
+        "<vstack>\n<!-- <label>Hidden</label>\n<box /> -->\n<label>Shown</label>\n</vstack>";
+    let Node::Element(root) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+    assert_eq!(root.children.len(), 1);
+
+    let Node::Element(label) = &root.children[0] else {
+        panic!("Expected label");
+    };
+    assert_eq!(label.span.line, 4);
+
+    assert!(fml::parse("<vstack><!-- no end </vstack>").is_err());
+}
+
+#[test]
+fn parse_boolean_attributes() {
+    let input = "<button disabled autofocus=false title='Say \"hi\"' class=\"a\" checked/>";
+    let Node::Element(button) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+
+    assert!(matches!(
+        button.get_attr("disabled"),
+        Some(AttributeValue::Boolean { value: true, .. })
+    ));
+    assert!(matches!(
+        button.get_attr("autofocus"),
+        Some(AttributeValue::Boolean { value: false, .. })
+    ));
+    assert!(matches!(
+        button.get_attr("checked"),
+        Some(AttributeValue::Boolean { value: true, .. })
+    ));
+    assert_eq!(button.get_attr("title").unwrap().to_string(), "Say \"hi\"");
+    assert_eq!(button.get_attr("class").unwrap().to_string(), "a");
+
+    // Quoted values stay strings
+    let Node::Element(label) = fml::parse("<label text=\"true\" />").unwrap() else {
+        panic!("Expected element");
+    };
+    assert!(matches!(
+        label.get_attr("text"),
+        Some(AttributeValue::String { .. })
+    ));
+}
+
+#[test]
+fn parse_fragments() {
+    let Node::Element(fragment) =
+        fml::parse("<>\n<label>A</label>\n<label>B</label>\n</>").unwrap()
+    else {
+        panic!("Expected element");
+    };
+    assert_eq!(fragment.kind, ElementKind::Fragment);
+    assert_eq!(fragment.children.len(), 2);
+
+    // Several top-level elements are wrapped in an implicit fragment
+    let input = "<props title=\"A\" />\n<label>{prop:title}</label>\n<button>Ok</button>";
+    let Node::Element(fragment) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+    assert_eq!(fragment.kind, ElementKind::Fragment);
+    assert_eq!(fragment.children.len(), 3);
+    assert_eq!((fragment.span.start, fragment.span.end), (0, input.len()));
+
+    let errors = fml::parse("<><label>A</label></vstack>").unwrap_err();
+    assert_eq!(
+        errors[0].kind(),
+        &ParseErrorKind::MismatchingClosingTag {
+            expected: String::new(),
+            found: "vstack".to_string()
+        }
+    );
+}
+
+#[test]
+fn parse_element_ids() {
+    fn ids(node: &Node, buf: &mut Vec<(String, ElementId)>) {
+        if let Node::Element(elem) = node {
+            buf.push((elem.kind.name().to_string(), elem.id));
+            for child in &elem.children {
+                ids(child, buf);
+            }
+        }
+    }
+
+    let collect = |input: &str| {
+        let mut buf = Vec::new();
+        ids(&fml::parse(input).unwrap(), &mut buf);
+        buf
+    };
+
+    let first = collect("<vstack><label>A</label><hstack><label>B</label></hstack></vstack>");
+    let unique = first.iter().map(|(_, id)| *id).collect::<HashSet<_>>();
+    assert_eq!(unique.len(), first.len());
+
+    // Same source gives same ids, and changes elsewhere don't move them
+    assert_eq!(
+        collect("<vstack><label>A</label><hstack><label>B</label></hstack></vstack>"),
+        first
+    );
+    let changed =
+        collect("<vstack><label>C</label><box /><hstack><label>B</label></hstack></vstack>");
+    assert_eq!(changed[3], first[2]);
+    assert_eq!(changed[4], first[3]);
+
+    // Other documents don't affect the ids
+    let _ = collect("<box><box /></box>");
+    assert_eq!(
+        collect("<vstack><label>A</label><hstack><label>B</label></hstack></vstack>"),
+        first
+    );
+}
+
+#[test]
+fn parse_clip_and_empty() {
+    let Node::Element(clip) =
+        fml::parse("<clip class=\"frame\"><empty width=10 /></clip>").unwrap()
+    else {
+        panic!("Expected element");
+    };
+    assert_eq!(clip.kind, ElementKind::Clip);
+
+    let Node::Element(empty) = &clip.children[0] else {
+        panic!("Expected empty");
+    };
+    assert_eq!(empty.kind, ElementKind::Empty);
+    assert_eq!(fml::format("<empty/>").unwrap(), "<empty />\n");
+}
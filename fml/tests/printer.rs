@@ -0,0 +1,175 @@
+use fml::{AttributeValue, Lexer, Node, ParseErrorKind, Parser};
+
+fn parse_with_comments(input: &str) -> Vec<Node<'_>> {
+    let mut lexer = Lexer::new(input);
+    let tokens = lexer.lex();
+    Parser::with_comments(tokens).parse().unwrap()
+}
+
+fn assert_round_trip(input: &str) {
+    let ast = parse_with_comments(input);
+    let printed = fml::print(&ast).unwrap();
+    assert_eq!(parse_with_comments(&printed), ast, "Printed:\n{printed}");
+    assert_eq!(fml::print(&parse_with_comments(&printed)).unwrap(), printed);
+}
+
+#[test]
+fn print_round_trip() {
+    for file in ["small", "large"] {
+        let input = std::fs::read_to_string(format!("./tests/data/{file}.fml")).unwrap();
+        assert_round_trip(&input);
+    }
+
+    assert_round_trip(
+        r#"<root>
+<if cond={int:count > 1 && !bool:hidden}><label>{int:count < 10 ? "few" : "many"}</label>
+<else/><label>None</label></if>
+<for each={list:items} as="item" index="i"><button onclick={remove(i)}>x</button></for>
+<box width=1.0 height=0.5 gap=4 title="Title" neg={-int:count} sub={int:count - 1} paren={(count)}/>
+<button disabled hidden=false title='Say "hi"'>Ok</button>
+<><label>A</label><box /></><></>
+</root>"#,
+    );
+}
+
+#[test]
+fn print_canonical() {
+    let input = "<vstack onclick={save}  class=\"app\" title=\"Main\"   id=\"main\">\n<label>Text</label>\n\n<hstack></hstack>\n</vstack>";
+    let expected = "<vstack id=\"main\" title=\"Main\" class=\"app\" onclick={save}>\n    <label>Text</label>\n    <hstack />\n</vstack>\n";
+
+    assert_eq!(fml::format(input).unwrap(), expected);
+    assert_eq!(fml::format(expected).unwrap(), expected);
+
+    let long = format!(
+        "<box {}/>",
+        (0..12)
+            .map(|i| format!("prop{i}=\"value\""))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    let formatted = fml::format(&long).unwrap();
+    assert!(formatted.starts_with("<box\n    prop0=\"value\"\n"));
+    assert!(formatted.ends_with("    prop11=\"value\"\n/>\n"));
+    assert_eq!(fml::parse(&formatted).unwrap(), fml::parse(&long).unwrap());
+}
+
+#[test]
+fn print_comments() {
+    let input = "// Header\n<vstack>\n  // First\n<label>One</label>\n      //Second\n</vstack>\n";
+    let expected =
+        "// Header\n<vstack>\n    // First\n    <label>One</label>\n    // Second\n</vstack>\n";
+
+    assert_eq!(fml::format(input).unwrap(), expected);
+
+    // Comments are not part of the tree by default
+    let Node::Element(root) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+    assert_eq!(root.children.len(), 1);
+}
+
+#[test]
+fn print_comments_in_tags() {
+    let input = "<vstack gap=4 // Spacing\n  <!-- Old: width=10 --> class=\"app\">\n<box // Empty\n/>\n</vstack>\n";
+    let expected = "<vstack\n    gap=4\n    class=\"app\"\n    // Spacing\n    <!-- Old: width=10 -->\n>\n    <box\n        // Empty\n    />\n</vstack>\n";
+
+    assert_eq!(fml::format(input).unwrap(), expected);
+    assert_round_trip(input);
+
+    let Node::Element(root) = &parse_with_comments(input)[0] else {
+        panic!("Expected element");
+    };
+    assert_eq!(root.comments.len(), 2);
+    assert_eq!(root.attributes.len(), 2);
+
+    // Comments are not part of the tree by default
+    let Node::Element(root) = fml::parse(input).unwrap() else {
+        panic!("Expected element");
+    };
+    assert!(root.comments.is_empty());
+}
+
+#[test]
+fn print_escapes() {
+    let input = "<vstack>\n<!-- <label>Old</label> -->\n<label>1 &lt; 2 &amp;amp; \\{x} {int:count} C:\\dir\\\\</label>\n</vstack>";
+    let expected = "<vstack>\n    <!-- <label>Old</label> -->\n    <label>1 &lt; 2 &amp;amp; \\{x} {int:count} C:\\dir\\\\</label>\n</vstack>\n";
+
+    assert_eq!(fml::format(input).unwrap(), expected);
+    assert_round_trip(input);
+}
+
+#[test]
+fn print_errors() {
+    assert!(fml::format("<vstack><label></vstack>").is_err());
+}
+
+#[test]
+fn print_float_literals() {
+    assert_round_trip(
+        "<box w={int:count / 2.0} h={1.0 + 0.5 * dbl:scale} c={x > 3.0 ? 1 : 2.0} />",
+    );
+    assert_eq!(
+        fml::format("<box w={int:count / 2.0}/>").unwrap(),
+        "<box w={int:count / 2.0} />\n"
+    );
+}
+
+#[test]
+fn print_quoted_colons() {
+    let formatted = fml::format("<box title='a: b' label=\"str:name\" value=str:name/>").unwrap();
+    assert_eq!(
+        formatted,
+        "<box title=\"a: b\" label=\"str:name\" value={str:name} />\n"
+    );
+    assert_round_trip("<box title='a: \"b\"' label=\"x:y\" />");
+
+    let Node::Element(elem) = fml::parse(&formatted).unwrap() else {
+        panic!("Expected element");
+    };
+    assert!(matches!(
+        elem.get_attr("title"),
+        Some(AttributeValue::String { value, .. }) if value == "a: b"
+    ));
+}
+
+#[test]
+fn print_variable_names() {
+    assert_round_trip("<box a=str:first-name b=x:y-z c=int:a:b d={user.name} />");
+    assert_eq!(
+        fml::format("<box a=str:first-name b=x:y-z />").unwrap(),
+        "<box a=str:first-name b=unknown:y-z />\n"
+    );
+
+    let Node::Element(mut elem) = fml::parse("<box a={x} />").unwrap() else {
+        panic!("Expected element");
+    };
+    elem.attributes[0].value = AttributeValue::Variable {
+        name: "str:first name".into(),
+        line: 1,
+        col: 8,
+    };
+    let errors = fml::print(&[Node::Element(elem)]).unwrap_err();
+    assert!(matches!(
+        errors[0].kind(),
+        ParseErrorKind::UnprintableValue(_)
+    ));
+}
+
+#[test]
+fn print_quotes() {
+    assert_round_trip("<box a='say \"hi\"' b=\"it's\" />");
+
+    let Node::Element(mut elem) = fml::parse("<box a=\"x\" />").unwrap() else {
+        panic!("Expected element");
+    };
+    elem.attributes[0].value = AttributeValue::String {
+        value: "it's \"quoted\"".into(),
+        line: 1,
+        col: 8,
+    };
+    let errors = fml::print(&[Node::Element(elem)]).unwrap_err();
+    assert!(matches!(
+        errors[0].kind(),
+        ParseErrorKind::UnprintableValue(_)
+    ));
+}
@@ -0,0 +1,121 @@
+use fml::{Diagnostic, DiagnosticKind, Severity, Validator, VariableType};
+
+const MAIN: &str = r#"<vstack class="app missing">
+    <label>{int:counter} {str:title}</label>
+    <button onclick={increase}>Increase</button>
+    <button onclick={remove(int:counter, row)}>Remove</button>
+    <for each={list:items} as="item" index="i">
+        <label>{item.name} {i}</label>
+    </for>
+    <panel title={str:counter} />
+    <missing />
+</vstack>"#;
+
+const PANEL: &str = r#"<vstack class="app">
+    <props title="Panel" />
+    <label>{prop:title} {prop:subtitle}</label>
+</vstack>"#;
+
+fn validator() -> Validator {
+    Validator::new()
+        .handlers(["increase", "remove", "unused_handler"])
+        .vars([
+            ("counter", VariableType::Integer),
+            ("title", VariableType::String),
+            ("items", VariableType::List),
+            ("unused_var", VariableType::Boolean),
+        ])
+        .components(["panel"])
+        .classes(["app"])
+}
+
+fn kinds(diagnostics: &[Diagnostic]) -> Vec<&DiagnosticKind> {
+    diagnostics.iter().map(|d| &d.kind).collect()
+}
+
+#[test]
+fn validate_project() {
+    let mut validator = validator();
+    validator.validate("main.fml", &fml::parse(MAIN).unwrap());
+    validator.validate("panel", &fml::parse(PANEL).unwrap());
+    let diagnostics = validator.finish();
+
+    assert_eq!(
+        kinds(&diagnostics),
+        vec![
+            &DiagnosticKind::UnknownClass("missing".to_string()),
+            &DiagnosticKind::UndeclaredVariable("row".to_string()),
+            &DiagnosticKind::TypeMismatch {
+                name: "counter".to_string(),
+                declared: VariableType::Integer,
+                found: VariableType::String,
+            },
+            &DiagnosticKind::UnknownComponent("missing".to_string()),
+            &DiagnosticKind::UnknownProp("subtitle".to_string()),
+            &DiagnosticKind::UnusedHandler("unused_handler".to_string()),
+            &DiagnosticKind::UnusedVariable("unused_var".to_string()),
+        ]
+    );
+
+    assert_eq!(
+        diagnostics[1].to_string(),
+        "main.fml:4:13: Variable 'row' not declared"
+    );
+    assert_eq!(diagnostics[0].kind.severity(), Severity::Warning);
+    assert_eq!(diagnostics[1].kind.severity(), Severity::Error);
+    assert_eq!(
+        diagnostics[5].to_string(),
+        "Handler 'unused_handler' is never used"
+    );
+}
+
+#[test]
+fn validate_handlers() {
+    let mut validator = Validator::new().handlers(["save"]);
+    let node =
+        fml::parse(r#"<box><button onclick={load}>Load</button><panel onsave="save" /></box>"#)
+            .unwrap();
+    validator.validate("main.fml", &node);
+    let diagnostics = validator.finish();
+
+    assert_eq!(
+        kinds(&diagnostics),
+        vec![
+            &DiagnosticKind::UnknownHandler("load".to_string()),
+            &DiagnosticKind::UnknownComponent("panel".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn validate_handler_props() {
+    let mut validator = Validator::new()
+        .handlers(["save", "load", "unused_handler"])
+        .components(["panel", "card"]);
+    let main = fml::parse(
+        r#"<box>
+    <label title="unused_handler">unused_handler</label>
+    <panel title="load" action="save" />
+    <card onopen="load" />
+</box>"#,
+    )
+    .unwrap();
+    let panel = fml::parse(
+        r#"<box>
+    <props title="" action="" />
+    <label>{prop:title}</label>
+    <card onopen={prop:action} />
+</box>"#,
+    )
+    .unwrap();
+    let card = fml::parse(r#"<button onclick={prop:onopen}>Open</button>"#).unwrap();
+
+    validator.validate("main.fml", &main);
+    validator.validate_component("panel", "panel.fml", &panel);
+    validator.validate_component("card", "card.fml", &card);
+
+    assert_eq!(
+        kinds(&validator.finish()),
+        vec![&DiagnosticKind::UnusedHandler("unused_handler".to_string())]
+    );
+}
@@ -0,0 +1,94 @@
+use fml::{Value, VariableName, VariableType};
+
+#[test]
+fn value_path() {
+    let todo = Value::map([
+        ("title", Value::from("Buy milk")),
+        ("tags", Value::from(vec!["home", "shop"])),
+    ]);
+
+    assert_eq!(todo.get_path("title"), Some(&Value::from("Buy milk")));
+    assert_eq!(todo.get_path("tags.1"), Some(&Value::from("shop")));
+    assert_eq!(todo.get_path("tags.2"), None);
+    assert_eq!(todo.get_path("title.x"), None);
+}
+
+#[test]
+fn value_display() {
+    let todo = Value::map([("done", Value::from(false)), ("id", Value::from(1))]);
+
+    assert_eq!(todo.to_string(), "{done: false, id: 1}");
+    assert_eq!(Value::from(vec![1.5, 2.0]).to_string(), "[1.5, 2]");
+}
+
+#[test]
+fn value_truthy() {
+    assert!(Value::from(1).is_truthy());
+    assert!(!Value::from(0.0).is_truthy());
+    assert!(!Value::from("").is_truthy());
+    assert!(Value::from(vec![true]).is_truthy());
+}
+
+#[test]
+fn value_parse() {
+    assert_eq!("true".parse(), Ok(Value::Bool(true)));
+    assert_eq!("-12".parse(), Ok(Value::Integer(-12)));
+    assert_eq!("1.5".parse(), Ok(Value::Float(1.5)));
+    assert_eq!("Hello, world".parse(), Ok(Value::from("Hello, world")));
+    assert_eq!(
+        "[1, \"two\", false]".parse(),
+        Ok(Value::List(vec![
+            Value::from(1),
+            Value::from("two"),
+            Value::from(false)
+        ]))
+    );
+    assert_eq!(
+        "{name: \"Jane\", tags: [a, b], age: 30}".parse(),
+        Ok(Value::map([
+            ("name", Value::from("Jane")),
+            ("tags", Value::from(vec!["a", "b"])),
+            ("age", Value::from(30)),
+        ]))
+    );
+    assert_eq!("[]".parse(), Ok(Value::List(Vec::new())));
+    assert!("[1, 2".parse::<Value>().is_err());
+    assert!("{name \"Jane\"}".parse::<Value>().is_err());
+    assert!("[1] 2".parse::<Value>().is_err());
+}
+
+#[test]
+fn value_nesting() {
+    let nested = format!("{}1{}", "[".repeat(64), "]".repeat(64));
+    assert!(nested.parse::<Value>().is_ok());
+
+    let deep = format!("{}1{}", "[".repeat(100_000), "]".repeat(100_000));
+    assert_eq!(
+        deep.parse::<Value>(),
+        Err("Value is nested too deep".to_string())
+    );
+    assert!(format!("{}{}", "{a: ".repeat(100), "}".repeat(100))
+        .parse::<Value>()
+        .is_err());
+
+    let value = (0..100).fold(Value::from(1), |v, _| Value::List(vec![v]));
+    assert!(value.to_string().contains("[...]"));
+}
+
+#[test]
+fn value_kind() {
+    assert_eq!(VariableName::from("list:items").kind, VariableType::List);
+    assert_eq!(VariableName::from("map:user").kind, VariableType::Map);
+    assert_eq!(VariableName::from("bool:done").kind, VariableType::Boolean);
+    assert_eq!(Value::from(vec![1, 2]).kind(), VariableType::List);
+    assert_eq!(Value::map([("a", 1)]).kind(), VariableType::Map);
+}
+
+#[test]
+fn value_try_from() {
+    assert_eq!(i64::try_from(Value::from(3)), Ok(3));
+    assert_eq!(f64::try_from(Value::from(3)), Ok(3.0));
+    assert_eq!(String::try_from(Value::from("a")), Ok("a".to_string()));
+    assert_eq!(i64::try_from(Value::from("3")), Err(Value::from("3")));
+    assert_eq!(bool::try_from(Value::from(1)), Err(Value::from(1)));
+}